@@ -0,0 +1,121 @@
+use model::SandPileModel;
+use model::sand_graph::{NodeIndex, SandGraph};
+use graphics::math;
+
+// How walker on an occupied node chooses the next node, node has outgoing edges
+pub trait WalkRule {
+    fn next_node(&mut self, graph: &SandGraph, node_idx: NodeIndex) -> NodeIndex;
+
+    // back to the initial state of the rule
+    fn clear(&mut self) {}
+}
+
+// Aggregation from walkers: walker released from source moves by the walk rule until it finds a free node
+// and occupies it, walker in sink is lost
+pub struct Aggregation<W: WalkRule> {
+    pub model: SandPileModel,
+    pub occupied: Vec<bool>,
+    pub rule: W,
+}
+
+// Distances are measured in the embedding from the origin node
+#[derive(Clone, Copy, Debug)]
+pub struct ClusterRadius {
+    pub occupied_count: usize,
+    // distance to the nearest free node, ball of this radius is completely occupied
+    pub inner: f32,
+    // distance to the farthest occupied node
+    pub outer: f32,
+    pub mean: f32,
+}
+
+impl<W: WalkRule> Aggregation<W> {
+    // walk on graph without sink in which every node is occupied never ends
+    pub const WALK_STEPS_LIMIT: usize = 100000000;
+
+    pub fn with_rule(model: SandPileModel, rule: W) -> Self {
+        let nodes_count = model.graph.nodes.len();
+        Aggregation { model, occupied: vec![false; nodes_count], rule }
+    }
+
+    pub fn clear(&mut self) {
+        for node_idx in self.model.graph.non_sink_nodes() {
+            self.occupied[node_idx] = false;
+        }
+        self.rule.clear();
+    }
+
+    // Releases one walker from source, returns node where it settled or None if it was lost in sink
+    pub fn walk(&mut self, source: NodeIndex) -> Option<NodeIndex> {
+        let mut node_idx = source;
+        for _ in 0..Self::WALK_STEPS_LIMIT {
+            if node_idx == SandGraph::SINK_NODE {
+                return None;
+            }
+            if ! self.occupied[node_idx] {
+                self.occupied[node_idx] = true;
+                return Some(node_idx);
+            }
+            if self.model.graph.nodes[node_idx].degree <= 0 {
+                return None;
+            }
+            node_idx = self.rule.next_node(&self.model.graph, node_idx);
+        }
+        None
+    }
+
+    // Releases walkers_count walkers one after another, returns how many of them settled
+    pub fn aggregate(&mut self, source: NodeIndex, walkers_count: usize) -> usize {
+        (0..walkers_count)
+            .filter(|_| self.walk(source).is_some())
+            .count()
+    }
+
+    pub fn add_walkers(&mut self, coords: math::Vec3d<f32>, walkers_count: usize) -> usize {
+        let source = self.model.embedding.get_node_by_coords(coords);
+        self.aggregate(source, walkers_count)
+    }
+
+    pub fn cluster_radius(&self, source: NodeIndex) -> ClusterRadius {
+        ClusterRadius::new(&self.model, &self.occupied, source)
+    }
+
+    // Occupied nodes get no sand and free ones degree - 1 grains, so SandPileView draws only the cluster
+    pub fn sync_sand(&self) {
+        let graph = &self.model.graph;
+        for node_idx in graph.non_sink_nodes() {
+            let sand = if self.occupied[node_idx] { 0 } else { graph.nodes[node_idx].degree - 1 };
+            graph.nodes[node_idx].sand.set(sand);
+        }
+    }
+}
+
+impl ClusterRadius {
+    pub fn new(model: &SandPileModel, occupied: &Vec<bool>, origin: NodeIndex) -> Self {
+        let (origin_coords, _) = model.embedding.get_node_info(origin);
+        let mut occupied_count = 0;
+        let mut inner = std::f32::MAX;
+        let mut outer = 0.0_f32;
+        let mut sum = 0.0;
+
+        for node_idx in model.graph.non_sink_nodes() {
+            let (coords, _) = model.embedding.get_node_info(node_idx);
+            let distance = vecmath::vec3_len(vecmath::vec3_sub(coords, origin_coords));
+            if occupied[node_idx] {
+                occupied_count += 1;
+                outer = outer.max(distance);
+                sum += distance;
+            } else {
+                inner = inner.min(distance);
+            }
+        }
+
+        let mean = if occupied_count > 0 { sum / occupied_count as f32 } else { 0.0 };
+        ClusterRadius { occupied_count, inner, outer, mean }
+    }
+
+    // 0 for a perfect ball
+    pub fn fluctuation(&self) -> f32 {
+        (self.outer - self.inner).max(0.0)
+    }
+}
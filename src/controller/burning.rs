@@ -0,0 +1,39 @@
+use model::spanning_tree::{self, SpanningTree};
+use super::SandPileController;
+
+// Burning bijection between spanning trees oriented to sink and recurrent configurations:
+// tree gives superstable configuration s by burning and recurrent one is max_stable - s.
+// It is Dhar's burning bijection on undirected graphs, on directed graphs without cycles
+// every stable configuration is recurrent and it still holds.
+// Usual node topplings are assumed, controller is left in the result.
+impl<'a> SandPileController<'a> {
+    pub fn recurrent_from_tree(&mut self, tree: &SpanningTree) -> Result<Vec<i32>, String> {
+        spanning_tree::check_burning_bijection(&self.model.graph)?;
+        let superstable = tree.to_superstable(&self.model.graph)?;
+        let recurrent = self.complement(&superstable);
+        self.set_configuration(&recurrent);
+        Ok(recurrent)
+    }
+
+    pub fn tree_from_recurrent(&mut self, recurrent: &Vec<i32>) -> Result<SpanningTree, String> {
+        spanning_tree::check_burning_bijection(&self.model.graph)?;
+        if ! self.is_recurrent(recurrent) {
+            return Err("configuration isn't recurrent".to_string());
+        }
+        let superstable = self.complement(recurrent);
+        SpanningTree::from_superstable(&self.model.graph, &superstable)
+    }
+
+    // Uniform random recurrent configuration: uniform spanning tree by Wilson's algorithm mapped by the bijection
+    pub fn random_recurrent(&mut self, seed: u64) -> Result<Vec<i32>, String> {
+        let tree = SpanningTree::wilson(&self.model.graph, seed)?;
+        self.recurrent_from_tree(&tree)
+    }
+
+    // max_stable - configuration
+    fn complement(&self, configuration: &Vec<i32>) -> Vec<i32> {
+        self.model.graph.non_sink_nodes().zip(configuration)
+            .map(|(node_idx, sand)| self.node_topplings[node_idx].threshold - 1 - sand)
+            .collect()
+    }
+}
@@ -0,0 +1,158 @@
+use model::SandPileModel;
+use model::sand_graph::{NodeIndex, SandGraph};
+use graphics::math;
+use std::collections::VecDeque;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ContinuousRule {
+    // Zhang model, node with mass >= threshold gives all its mass away, weight / degree of it along every edge
+    Zhang { threshold: f64 },
+    // Levine-Peres divisible sandpile, node keeps mass 1 and gives away the excess, weight / degree of it along every edge.
+    // Exact stabilization takes infinitely many topplings, so node is stable when excess is at most tolerance
+    Divisible { tolerance: f64 },
+}
+
+// Real valued counterpart of SandPileController, mass lives next to the model,
+// sand in the model is only used to show mass with SandPileView, see sync_sand
+pub struct ContinuousController {
+    pub model: SandPileModel,
+    pub mass: Vec<f64>,
+    rule: ContinuousRule,
+    // FIFO order, with stack divisible sandpile keeps passing tiny excess back and forth
+    queue: VecDeque<NodeIndex>,
+    is_in_queue: Vec<bool>,
+}
+
+impl ContinuousController {
+    pub const TOPPLE_ROUNDS: usize = 10000000;
+
+    pub fn new(model: SandPileModel, rule: ContinuousRule) -> Result<Self, String> {
+        match rule {
+            ContinuousRule::Zhang { threshold } if ! (threshold > 0.0) =>
+                return Err(format!("{:?}: threshold has to be positive", rule)),
+            ContinuousRule::Divisible { tolerance } if ! (tolerance > 0.0) =>
+                return Err(format!("{:?}: tolerance has to be positive, exact stabilization never ends", rule)),
+            _ => (),
+        }
+        let nodes_count = model.graph.nodes.len();
+        let mut is_in_queue = vec![false; nodes_count];
+        is_in_queue[SandGraph::SINK_NODE] = true;
+
+        Ok(ContinuousController { model, mass: vec![0.0; nodes_count], rule, queue: VecDeque::new(), is_in_queue })
+    }
+
+    pub fn add_mass(&mut self, coords: math::Vec3d<f32>, mass: f64) {
+        let node_idx = self.model.embedding.get_node_by_coords(coords);
+        self.add_mass_to_node(node_idx, mass);
+    }
+
+    pub fn add_mass_to_node(&mut self, node_idx: NodeIndex, mass: f64) {
+        self.mass[node_idx] += mass;
+        self.add_node_to_queue_if_needed(node_idx);
+    }
+
+    pub fn add_mass_to_all_nodes(&mut self, mass: f64) {
+        for node_idx in self.model.graph.non_sink_nodes() {
+            self.add_mass_to_node(node_idx, mass);
+        }
+    }
+
+    pub fn clear_mass(&mut self) {
+        for node_idx in self.model.graph.non_sink_nodes() {
+            self.mass[node_idx] = 0.0;
+            self.is_in_queue[node_idx] = false;
+        }
+        self.queue.clear();
+    }
+
+    // mass which was lost to sink
+    pub fn sink_mass(&self) -> f64 {
+        self.mass[SandGraph::SINK_NODE]
+    }
+
+    pub fn is_relaxed(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    fn is_unstable(&self, node_idx: NodeIndex) -> bool {
+        if self.model.graph.nodes[node_idx].degree <= 0 {
+            return false;
+        }
+        match self.rule {
+            ContinuousRule::Zhang { threshold } => self.mass[node_idx] >= threshold,
+            ContinuousRule::Divisible { tolerance } => self.mass[node_idx] > 1.0 + tolerance,
+        }
+    }
+
+    fn add_node_to_queue_if_needed(&mut self, node_idx: NodeIndex) {
+        if ! self.is_in_queue[node_idx] && self.is_unstable(node_idx) {
+            self.queue.push_back(node_idx);
+            self.is_in_queue[node_idx] = true;
+        }
+    }
+
+    fn topple(&mut self, node_idx: NodeIndex) {
+        let given_mass = match self.rule {
+            ContinuousRule::Zhang { .. } => self.mass[node_idx],
+            ContinuousRule::Divisible { .. } => self.mass[node_idx] - 1.0,
+        };
+        self.mass[node_idx] -= given_mass;
+
+        let degree = self.model.graph.nodes[node_idx].degree as f64;
+        let successors: Vec<(i32, NodeIndex)> = self.model.graph.successors(node_idx).collect();
+        for (weight, neighbour_node_idx) in successors {
+            self.mass[neighbour_node_idx] += given_mass * weight as f64 / degree;
+            self.add_node_to_queue_if_needed(neighbour_node_idx);
+        }
+    }
+
+    // Does at most rounds queue pops, returns topplings count
+    fn relax(&mut self, rounds: usize) -> usize {
+        let mut topplings_count = 0;
+        for _ in 0..rounds {
+            if let Some(node_idx) = self.queue.pop_front() {
+                self.is_in_queue[node_idx] = false;
+                if self.is_unstable(node_idx) {
+                    self.topple(node_idx);
+                    topplings_count += 1;
+                }
+                self.add_node_to_queue_if_needed(node_idx);
+            } else {
+                break
+            }
+        }
+        topplings_count
+    }
+
+    pub fn update(&mut self) {
+        self.relax(Self::TOPPLE_ROUNDS);
+    }
+
+    // Relaxes completely, returns topplings count, None if it isn't relaxed after max_topplings topplings.
+    // Zhang model on graph without sink never stabilizes when there is enough mass
+    pub fn stabilize(&mut self, max_topplings: usize) -> Option<usize> {
+        let mut topplings_count = 0;
+        while ! self.is_relaxed() {
+            if topplings_count >= max_topplings {
+                return None;
+            }
+            topplings_count += self.relax(max_topplings - topplings_count);
+        }
+        Some(topplings_count)
+    }
+
+    // Writes mass to sand of the model, so that full node (mass at threshold, or 1 for divisible sandpile)
+    // has degree - 1 grains and is drawn like the maximal stable node of the abelian sandpile
+    pub fn sync_sand(&self) {
+        let capacity = match self.rule {
+            ContinuousRule::Zhang { threshold } => threshold,
+            ContinuousRule::Divisible { .. } => 1.0,
+        };
+        let graph = &self.model.graph;
+        for node_idx in graph.non_sink_nodes() {
+            let degree = graph.nodes[node_idx].degree;
+            let sand = (self.mass[node_idx] / capacity * degree as f64).floor() as i32;
+            graph.nodes[node_idx].sand.set(sand.min(degree - 1).max(0));
+        }
+    }
+}
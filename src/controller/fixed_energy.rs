@@ -0,0 +1,139 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+use model::sand_graph::{NodeIndex, SandGraph};
+use super::{SandPileController, TopplingRule};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FixedEnergyUpdate {
+    // every active node topples at once
+    Parallel,
+    // active nodes topple one by one in random order, nodes activated during the step wait for the next one
+    RandomSequential,
+}
+
+#[derive(Clone, Debug)]
+pub struct ActivityReport {
+    // fraction of active nodes before every step, last value is taken after the last step
+    pub active_density: Vec<f64>,
+    // number of steps until there were no active nodes, None if the system was still active after max_steps
+    pub absorption_time: Option<usize>,
+}
+
+// Fixed-energy sandpile: total amount of sand is conserved, so it is meant for graphs without edges to sink,
+// like TorusLattice, and node topplings which lose nothing. On graphs with sink grains sent to sink are lost as usual.
+impl<'a> SandPileController<'a> {
+    // Drops round(density * nodes count) grains one by one on uniformly random nodes, previous sand is removed
+    pub fn set_sand_density<R: Rng>(&mut self, density: f64, rng: &mut R) {
+        self.clear_sand();
+        let nodes_count = self.model.graph.nodes.len() - 1;
+        let grains_count = (density * nodes_count as f64).round() as usize;
+        for _ in 0..grains_count {
+            let node = &self.model.graph.nodes[rng.gen_range(1, nodes_count + 1)];
+            node.sand.set(node.sand.get() + 1);
+        }
+    }
+
+    pub fn total_sand(&self) -> i64 {
+        let graph = &self.model.graph;
+        graph.non_sink_nodes().map(|node_idx| graph.nodes[node_idx].sand.get() as i64).sum()
+    }
+
+    pub fn active_nodes(&self) -> Vec<NodeIndex> {
+        let graph = &self.model.graph;
+        graph.non_sink_nodes()
+            .filter(|node_idx| graph.nodes[*node_idx].sand.get() >= self.node_topplings[*node_idx].threshold)
+            .collect()
+    }
+
+    // Runs until there are no active nodes or for max_steps steps.
+    // Grains are distributed by the current toppling rule, stochastic rule draws from rng instead of controller's own.
+    pub fn run_fixed_energy<R: Rng>(&mut self, update: FixedEnergyUpdate, max_steps: usize, rng: &mut R) -> ActivityReport {
+        let nodes_count = (self.model.graph.nodes.len() - 1).max(1);
+        let mut active_density = Vec::new();
+        let mut absorption_time = None;
+
+        let mut received = vec![0; self.model.graph.nodes.len()];
+        for step in 0..=max_steps {
+            let mut active = self.active_nodes();
+            active_density.push(active.len() as f64 / nodes_count as f64);
+            if active.is_empty() {
+                absorption_time = Some(step);
+                break;
+            }
+            if step == max_steps {
+                break;
+            }
+
+            match update {
+                FixedEnergyUpdate::Parallel => {
+                    for node_idx in &active {
+                        self.send_grains(*node_idx, &mut received, rng);
+                    }
+                    for node_idx in self.model.graph.non_sink_nodes() {
+                        let sand = &self.model.graph.nodes[node_idx].sand;
+                        sand.set(sand.get() + received[node_idx]);
+                        received[node_idx] = 0;
+                    }
+                    self.flush_sink(&mut received);
+                },
+                FixedEnergyUpdate::RandomSequential => {
+                    active.shuffle(rng);
+                    for node_idx in &active {
+                        if self.model.graph.nodes[*node_idx].sand.get() < self.node_topplings[*node_idx].threshold {
+                            continue;
+                        }
+                        self.send_grains(*node_idx, &mut received, rng);
+                        for (_, neighbour_node_idx) in self.model.graph.successors(*node_idx) {
+                            let sand = &self.model.graph.nodes[neighbour_node_idx].sand;
+                            sand.set(sand.get() + received[neighbour_node_idx]);
+                            received[neighbour_node_idx] = 0;
+                        }
+                        self.flush_sink(&mut received);
+                    }
+                },
+            }
+        }
+
+        self.need_update_stack = true;
+        ActivityReport { active_density, absorption_time }
+    }
+
+    // Topples node_idx: removes grains from it and adds what its neighbours and sink get to received
+    fn send_grains<R: Rng>(&mut self, node_idx: NodeIndex, received: &mut Vec<i32>, rng: &mut R) {
+        let graph = &self.model.graph;
+        let node = &graph.nodes[node_idx];
+        let node_toppling = &self.node_topplings[node_idx];
+
+        match self.toppling_rule {
+            TopplingRule::Deterministic => self.send_grains_by_weights(node_idx, received),
+            TopplingRule::Stochastic { .. } => {
+                node.sand.set(node.sand.get() - node_toppling.sent - node_toppling.lost);
+                received[SandGraph::SINK_NODE] += node_toppling.lost;
+                let successors: Vec<(i32, NodeIndex)> = graph.successors(node_idx).collect();
+                for _ in 0..node_toppling.sent {
+                    received[Self::random_successor(&successors, node.degree, rng)] += 1;
+                }
+            },
+        }
+    }
+
+    // Grains sent or lost to sink are added to its sand
+    pub(super) fn flush_sink(&self, received: &mut Vec<i32>) {
+        let sink = &self.model.graph.nodes[SandGraph::SINK_NODE];
+        sink.sand.set(sink.sand.get() + received[SandGraph::SINK_NODE]);
+        received[SandGraph::SINK_NODE] = 0;
+    }
+
+    // Same as send_grains with deterministic rule
+    pub(super) fn send_grains_by_weights(&mut self, node_idx: NodeIndex, received: &mut Vec<i32>) {
+        let graph = &self.model.graph;
+        let node = &graph.nodes[node_idx];
+        let node_toppling = &self.node_topplings[node_idx];
+        node.sand.set(node.sand.get() - node_toppling.sent - node_toppling.lost);
+        received[SandGraph::SINK_NODE] += node_toppling.lost;
+
+        for (share, neighbour_node_idx) in Self::shares_by_weights(graph, node_idx, node_toppling.sent, &mut self.rotors[node_idx]) {
+            received[neighbour_node_idx] += share;
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use num_bigint::BigInt;
+use model::sand_graph::{NodeIndex, SandGraph};
+use model::sandpile_group;
+use graphics::math;
+use super::SandPileController;
+use super::parallel::PeriodReport;
+
+// Sandpile group on recurrent configurations, sum is the stabilized pointwise sum.
+// Configurations are sand of non-sink nodes as returned by configuration().
+// Usual node topplings are assumed and grains are always sent by weights.
+// Every operation stabilizes on the controller's graph, so the controller is left in its result.
+impl<'a> SandPileController<'a> {
+    pub fn set_configuration(&mut self, configuration: &Vec<i32>) {
+        let graph = &self.model.graph;
+        for (node_idx, sand) in graph.non_sink_nodes().zip(configuration) {
+            graph.nodes[node_idx].sand.set(*sand);
+        }
+        self.need_update_stack = true;
+    }
+
+    // Returns number of topplings
+    pub fn stabilize(&mut self) -> usize {
+        let topplings_count = self.relax_except(SandGraph::SINK_NODE).len();
+        self.need_update_stack = true;
+        topplings_count
+    }
+
+    pub fn stabilized(&mut self, configuration: &Vec<i32>) -> Vec<i32> {
+        self.set_configuration(configuration);
+        self.stabilize();
+        self.configuration()
+    }
+
+    // 2 * max_stable - stab(2 * max_stable), it is equivalent to zero and not smaller than max_stable
+    fn zero_equivalent(&mut self) -> Vec<i32> {
+        let double_max_stable: Vec<i32> = self.model.graph.non_sink_nodes()
+            .map(|node_idx| 2 * (self.node_topplings[node_idx].threshold - 1))
+            .collect();
+        let stabilized = self.stabilized(&double_max_stable);
+        double_max_stable.iter().zip(&stabilized).map(|(a, b)| a - b).collect()
+    }
+
+    pub fn identity(&mut self) -> Vec<i32> {
+        let zero_equivalent = self.zero_equivalent();
+        self.stabilized(&zero_equivalent)
+    }
+
+    pub fn group_sum(&mut self, a: &Vec<i32>, b: &Vec<i32>) -> Vec<i32> {
+        let sum = a.iter().zip(b).map(|(x, y)| x + y).collect();
+        self.stabilized(&sum)
+    }
+
+    // Stable configuration is recurrent if adding identity doesn't change it
+    pub fn is_recurrent(&mut self, configuration: &Vec<i32>) -> bool {
+        let identity = self.identity();
+        let stabilized = self.stabilized(configuration);
+        stabilized == *configuration && self.group_sum(configuration, &identity) == *configuration
+    }
+
+    // Recurrent configuration equivalent to -configuration: stab(z - stab(c) + identity),
+    // where z is equivalent to zero and not smaller than stable stab(c), so z - stab(c) is not negative
+    pub fn inverse(&mut self, configuration: &Vec<i32>) -> Vec<i32> {
+        let identity = self.identity();
+        let zero_equivalent = self.zero_equivalent();
+        let stabilized = self.stabilized(configuration);
+        let negated = zero_equivalent.iter().zip(&stabilized).map(|(z, c)| z - c).collect();
+        self.group_sum(&negated, &identity)
+    }
+
+    // k-th power by repeated doubling, negative k gives power of the inverse and zero gives identity
+    pub fn power(&mut self, configuration: &Vec<i32>, k: i64) -> Vec<i32> {
+        let mut base = if k < 0 { self.inverse(configuration) } else { configuration.clone() };
+        let mut exponent = k.abs();
+        let mut result = self.identity();
+        while exponent > 0 {
+            if exponent % 2 == 1 {
+                result = self.group_sum(&result, &base);
+            }
+            base = self.group_sum(&base, &base);
+            exponent /= 2;
+        }
+        self.set_configuration(&result);
+        result
+    }
+
+    // Exact order of the class of configuration, it doesn't depend on toppling
+    pub fn element_order(&self, configuration: &Vec<i32>) -> BigInt {
+        sandpile_group::element_order(&self.model.graph, configuration)
+    }
+
+    // Adds one grain to node and stabilizes until a configuration repeats, None if it didn't happen in max_steps steps.
+    // Starting from recurrent configuration transient is 0 and period is the order of the grain at node
+    pub fn find_grain_orbit_period(&mut self, node_idx: NodeIndex, max_steps: usize) -> Option<PeriodReport> {
+        self.stabilize();
+        let mut visited: HashMap<Vec<i32>, usize> = HashMap::new();
+        visited.insert(self.configuration(), 0);
+
+        for step in 1..=max_steps {
+            let sand = &self.model.graph.nodes[node_idx].sand;
+            sand.set(sand.get() + 1);
+            self.stabilize();
+            let configuration = self.configuration();
+            if let Some(first_step) = visited.get(&configuration) {
+                return Some(PeriodReport { transient: *first_step, period: step - first_step });
+            }
+            visited.insert(configuration, step);
+        }
+        None
+    }
+
+    // Animated orbit: update() adds one grain to node every time sand is relaxed and pipeline is empty,
+    // None stops it
+    pub fn set_grain_orbit(&mut self, node_idx: Option<NodeIndex>) {
+        self.orbit_node = node_idx;
+    }
+
+    pub fn set_grain_orbit_by_coords(&mut self, coords: math::Vec3d<f32>) {
+        let node_idx = self.model.embedding.get_node_by_coords(coords);
+        self.set_grain_orbit(Some(node_idx));
+    }
+}
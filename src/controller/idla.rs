@@ -0,0 +1,33 @@
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use model::SandPileModel;
+use model::sand_graph::{NodeIndex, SandGraph};
+use super::SandPileController;
+use super::aggregation::{Aggregation, WalkRule};
+
+// Internal diffusion-limited aggregation: walker does simple random walk from the origin,
+// edge is chosen with probability weight / degree
+pub type InternalDla = Aggregation<RandomWalk>;
+
+pub struct RandomWalk {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl WalkRule for RandomWalk {
+    fn next_node(&mut self, graph: &SandGraph, node_idx: NodeIndex) -> NodeIndex {
+        let successors: Vec<(i32, NodeIndex)> = graph.successors(node_idx).collect();
+        SandPileController::random_successor(&successors, graph.nodes[node_idx].degree, &mut self.rng)
+    }
+
+    // cleared aggregation repeats the same walks
+    fn clear(&mut self) {
+        self.rng = StdRng::seed_from_u64(self.seed);
+    }
+}
+
+impl Aggregation<RandomWalk> {
+    pub fn new(model: SandPileModel, seed: u64) -> Self {
+        Aggregation::with_rule(model, RandomWalk { seed, rng: StdRng::seed_from_u64(seed) })
+    }
+}
@@ -1,6 +1,46 @@
-use model::SandPileModel;
+use model::{SandPileModel, WeightDistribution};
 use model::sand_graph::{NodeIndex, SandGraph, NodeData};
 use graphics::math;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+mod fixed_energy;
+mod continuous;
+mod aggregation;
+mod rotor_router;
+mod idla;
+mod parallel;
+mod waves;
+mod group;
+mod burning;
+
+pub use self::fixed_energy::{FixedEnergyUpdate, ActivityReport};
+pub use self::continuous::{ContinuousController, ContinuousRule};
+pub use self::aggregation::{Aggregation, WalkRule, ClusterRadius};
+pub use self::rotor_router::{RotorRouter, Rotors};
+pub use self::idla::{InternalDla, RandomWalk};
+pub use self::parallel::PeriodReport;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TopplingRule {
+    // BTW, unstable node sends weight grains along every outgoing edge
+    Deterministic,
+    // Manna, unstable node sends its grains one by one,
+    // every grain goes along an edge chosen with probability weight / degree
+    Stochastic { seed: u64 },
+}
+
+// How a node topples, NodeToppling::new(degree) is the usual sandpile
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NodeToppling {
+    // node is unstable when it has at least threshold grains
+    pub threshold: i32,
+    // grains sent to neighbours, deterministic rule sends weight * (sent / degree) grains along every edge
+    // and the rest sent % degree one per parallel edge, taking edges in turn
+    pub sent: i32,
+    // grains which go to sink on every toppling in addition to sent ones
+    pub lost: i32,
+}
 
 pub struct SandPileController<'a> {
     pipeline: Vec<&'a Fn(i32, i32) -> i32>,
@@ -8,6 +48,44 @@ pub struct SandPileController<'a> {
     stack: Vec<NodeIndex>,
     is_in_stack: Vec<bool>,
     need_update_stack: bool,
+    toppling_rule: TopplingRule,
+    rng: StdRng,
+    node_topplings: Vec<NodeToppling>,
+    // parallel edge which gets the next of the grains left from sent % degree
+    rotors: Vec<i32>,
+    orbit_node: Option<NodeIndex>,
+}
+
+impl NodeToppling {
+    pub fn new(degree: i32) -> Self {
+        NodeToppling { threshold: degree, sent: degree, lost: 0 }
+    }
+
+    // node needs threshold grains to topple, but sends only degree of them, the rest stays on the node
+    pub fn sticky(degree: i32, threshold: i32) -> Self {
+        NodeToppling { threshold, sent: degree, lost: 0 }
+    }
+
+    // every toppling loses lost grains to sink
+    pub fn dissipative(degree: i32, lost: i32) -> Self {
+        NodeToppling { threshold: degree + lost, sent: degree, lost }
+    }
+
+    fn check(&self, degree: i32) -> Result<(), String> {
+        if self.sent < 0 || self.lost < 0 {
+            return Err(format!("{:?}: sent and lost grains can't be negative", self));
+        }
+        if self.sent + self.lost == 0 {
+            return Err(format!("{:?}: toppling has to move at least one grain", self));
+        }
+        if self.sent + self.lost > self.threshold {
+            return Err(format!("{:?}: toppling takes more than threshold grains, sand would become negative", self));
+        }
+        if degree == 0 && self.sent != 0 {
+            return Err(format!("{:?}: node without outgoing edges can't send grains", self));
+        }
+        Ok(())
+    }
 }
 
 impl<'a> SandPileController<'a> {
@@ -20,7 +98,58 @@ impl<'a> SandPileController<'a> {
         is_in_stack[SandGraph::SINK_NODE] = true;
         let pipeline: Vec<&'a Fn(i32, i32) -> i32> = Vec::new();
 
-        SandPileController { pipeline, model, stack, is_in_stack, need_update_stack}
+        let toppling_rule = TopplingRule::Deterministic;
+        let rng = StdRng::seed_from_u64(0);
+        let node_topplings = model.graph.nodes.iter().map(|node| NodeToppling::new(node.degree)).collect();
+        let rotors = vec![0; model.graph.nodes.len()];
+
+        let orbit_node = None;
+
+        SandPileController { pipeline, model, stack, is_in_stack, need_update_stack, toppling_rule, rng, node_topplings, rotors, orbit_node }
+    }
+
+    pub fn node_toppling(&self, node_idx: NodeIndex) -> NodeToppling {
+        self.node_topplings[node_idx]
+    }
+
+    pub fn set_node_toppling(&mut self, node_idx: NodeIndex, node_toppling: NodeToppling) -> Result<(), String> {
+        node_toppling.check(self.model.graph.nodes[node_idx].degree)
+            .map_err(|e| format!("node {}: {}", node_idx, e))?;
+        self.node_topplings[node_idx] = node_toppling;
+        self.need_update_stack = true;
+        Ok(())
+    }
+
+    // f(node_idx, degree), nothing is changed if some toppling is wrong
+    pub fn set_node_topplings(&mut self, f: &Fn(NodeIndex, i32) -> NodeToppling) -> Result<(), String> {
+        let mut node_topplings = self.node_topplings.clone();
+        for node_idx in self.model.graph.non_sink_nodes() {
+            let degree = self.model.graph.nodes[node_idx].degree;
+            let node_toppling = f(node_idx, degree);
+            node_toppling.check(degree)
+                .map_err(|e| format!("node {}: {}", node_idx, e))?;
+            node_topplings[node_idx] = node_toppling;
+        }
+        self.node_topplings = node_topplings;
+        self.need_update_stack = true;
+        Ok(())
+    }
+
+    // Randomizes weights of the model, see SandPileModel::randomize_weights. Degrees change,
+    // so every node gets the usual toppling for its new degree
+    pub fn randomize_weights(&mut self, distribution: WeightDistribution, symmetric: bool, seed: u64) -> Result<(), String> {
+        self.model.randomize_weights(distribution, symmetric, seed)?;
+        self.node_topplings = self.model.graph.nodes.iter().map(|node| NodeToppling::new(node.degree)).collect();
+        self.rotors = vec![0; self.model.graph.nodes.len()];
+        self.need_update_stack = true;
+        Ok(())
+    }
+
+    pub fn set_toppling_rule(&mut self, toppling_rule: TopplingRule) {
+        if let TopplingRule::Stochastic { seed } = toppling_rule {
+            self.rng = StdRng::seed_from_u64(seed);
+        }
+        self.toppling_rule = toppling_rule;
     }
 
     pub fn set_pipeline(&mut self, pipeline: Vec<&'a Fn(i32, i32) -> i32>) {
@@ -47,8 +176,7 @@ impl<'a> SandPileController<'a> {
         let graph = &mut self.model.graph;
         for node_idx in graph.non_sink_nodes() {
             let sand = &graph.nodes[node_idx].sand;
-            let degree = graph.nodes[node_idx].degree;
-            sand.set(degree - 1);
+            sand.set(self.node_topplings[node_idx].threshold - 1);
         }
         self.need_update_stack = true;
     }
@@ -75,7 +203,7 @@ impl<'a> SandPileController<'a> {
         for node_idx in graph.non_sink_nodes() {
             let node = &graph.nodes[node_idx];
 
-            if node.sand.get() >= node.degree {
+            if node.sand.get() >= self.node_topplings[node_idx].threshold {
                 self.stack.push(node_idx);
                 self.is_in_stack[node_idx] = true;
             }
@@ -84,22 +212,58 @@ impl<'a> SandPileController<'a> {
     }
 
     // todo do something with this abomination
-    fn add_node_to_stack_if_needed(stack: &mut Vec<NodeIndex>, is_in_stack: &mut Vec<bool>, node: &NodeData, node_idx: NodeIndex) {
-        if ! is_in_stack[node_idx] && node.sand.get() >= node.degree {
+    fn add_node_to_stack_if_needed(stack: &mut Vec<NodeIndex>, is_in_stack: &mut Vec<bool>, node: &NodeData, threshold: i32, node_idx: NodeIndex) {
+        if ! is_in_stack[node_idx] && node.sand.get() >= threshold {
             stack.push(node_idx);
             is_in_stack[node_idx] = true;
         }
     }
 
+    // edge is chosen with probability weight / degree
+    fn random_successor<R: Rng>(successors: &Vec<(i32, NodeIndex)>, degree: i32, rng: &mut R) -> NodeIndex {
+        let mut grain = rng.gen_range(0, degree);
+        for (weight, successor_idx) in successors {
+            if grain < *weight {
+                return *successor_idx;
+            }
+            grain -= *weight;
+        }
+        SandGraph::SINK_NODE
+    }
+
+    // Deterministic rule sends weight * (sent / degree) grains along every edge, the rest sent % degree grains
+    // go one per parallel edge starting from the rotor, which then moves past them. Like in rotor-router
+    // every edge gets grains in turn, so the rest can't circle between nodes forever
+    fn shares_by_weights<'b>(graph: &'b SandGraph, node_idx: NodeIndex, sent: i32, rotor: &mut i32)
+                             -> impl Iterator<Item = (i32, NodeIndex)> + 'b {
+        let degree = graph.nodes[node_idx].degree;
+        let multiplier = if degree > 0 { sent / degree } else { 0 };
+        let remainder = sent - multiplier * degree;
+        let first = *rotor;
+        if degree > 0 {
+            *rotor = (first + remainder) % degree;
+        }
+
+        // parallel edges of an edge are [start, start + weight), grains go to [first, first + remainder) modulo degree
+        let mut start = 0;
+        graph.successors(node_idx).map(move |(weight, neighbour_node_idx)| {
+            let overlap = |lo: i32, hi: i32| (hi.min(start + weight) - lo.max(start)).max(0);
+            let extra = overlap(first, first + remainder) + overlap(first - degree, first + remainder - degree);
+            start += weight;
+            (weight * multiplier + extra, neighbour_node_idx)
+        })
+    }
+
     pub fn is_relaxed(&self) -> bool {
         (! self.need_update_stack) && self.stack.is_empty()
     }
 
+    // f(sand, threshold), threshold is degree unless node toppling was changed
     fn change_sand_by_function(&mut self, f: &Fn(i32, i32) -> i32) {
         for node_idx in self.model.graph.non_sink_nodes() {
             let current_sand = self.model.graph.nodes[node_idx].sand.get();
-            let degree = self.model.graph.nodes[node_idx].degree;
-            let new_sand = f(current_sand, degree);
+            let threshold = self.node_topplings[node_idx].threshold;
+            let new_sand = f(current_sand, threshold);
             self.model.graph.nodes[node_idx].sand.set(new_sand);
         }
         self.need_update_stack = true;
@@ -113,24 +277,47 @@ impl<'a> SandPileController<'a> {
         if self.is_relaxed() {
             if let Some(func) = self.pipeline.pop() {
                 self.change_sand_by_function(&func);
+            } else if let Some(node_idx) = self.orbit_node {
+                let sand = &self.model.graph.nodes[node_idx].sand;
+                sand.set(sand.get() + 1);
+                self.need_update_stack = true;
             }
             return;
         }
 
         let graph = & self.model.graph;
+        let node_topplings = &self.node_topplings;
+        let rotors = &mut self.rotors;
         for _ in 0..Self::TOPPLE_ROUNDS {
             if let Some(node_idx) = self.stack.pop() {
                 let node = &graph.nodes[node_idx];
-                for (weight, neighbour_node_idx) in graph.successors(node_idx) {
-                    let neighbour = & graph.nodes[neighbour_node_idx];
-                    neighbour.sand.set(neighbour.sand.get() + weight);
+                let node_toppling = &node_topplings[node_idx];
+                match self.toppling_rule {
+                    TopplingRule::Deterministic => {
+                        for (share, neighbour_node_idx) in Self::shares_by_weights(graph, node_idx, node_toppling.sent, &mut rotors[node_idx]) {
+                            let neighbour = & graph.nodes[neighbour_node_idx];
+                            neighbour.sand.set(neighbour.sand.get() + share);
+
+                            SandPileController::add_node_to_stack_if_needed(&mut self.stack, &mut self.is_in_stack, neighbour, node_topplings[neighbour_node_idx].threshold, neighbour_node_idx);
+                        }
+                    },
+                    TopplingRule::Stochastic { .. } => {
+                        let successors: Vec<(i32, NodeIndex)> = graph.successors(node_idx).collect();
+                        for _ in 0..node_toppling.sent {
+                            let neighbour_node_idx = Self::random_successor(&successors, node.degree, &mut self.rng);
+                            let neighbour = & graph.nodes[neighbour_node_idx];
+                            neighbour.sand.set(neighbour.sand.get() + 1);
 
-                    SandPileController::add_node_to_stack_if_needed(&mut self.stack, &mut self.is_in_stack, neighbour, neighbour_node_idx);
+                            SandPileController::add_node_to_stack_if_needed(&mut self.stack, &mut self.is_in_stack, neighbour, node_topplings[neighbour_node_idx].threshold, neighbour_node_idx);
+                        }
+                    },
                 }
-                node.sand.set(node.sand.get() - node.degree);
+                let sink = &graph.nodes[SandGraph::SINK_NODE];
+                sink.sand.set(sink.sand.get() + node_toppling.lost);
+                node.sand.set(node.sand.get() - node_toppling.sent - node_toppling.lost);
                 self.is_in_stack[node_idx] = false;
 
-                SandPileController::add_node_to_stack_if_needed(&mut self.stack, &mut self.is_in_stack, node, node_idx);
+                SandPileController::add_node_to_stack_if_needed(&mut self.stack, &mut self.is_in_stack, node, node_toppling.threshold, node_idx);
             } else {
                 break
             }
@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+use super::SandPileController;
+
+// Configurations at steps transient and transient + period are equal.
+// Stable configuration is a fixed point, so its period is 1
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PeriodReport {
+    pub transient: usize,
+    pub period: usize,
+}
+
+// Synchronous parallel chip-firing: on every step all unstable nodes fire at once.
+// Grains are always sent by weights, stochastic toppling rule is ignored here.
+impl<'a> SandPileController<'a> {
+    // Returns number of nodes which fired
+    pub fn parallel_step(&mut self) -> usize {
+        let active = self.active_nodes();
+        let mut received = vec![0; self.model.graph.nodes.len()];
+        for node_idx in &active {
+            self.send_grains_by_weights(*node_idx, &mut received);
+        }
+        for node_idx in self.model.graph.non_sink_nodes() {
+            let sand = &self.model.graph.nodes[node_idx].sand;
+            sand.set(sand.get() + received[node_idx]);
+        }
+        self.flush_sink(&mut received);
+        self.need_update_stack = true;
+        active.len()
+    }
+
+    // Sand of non-sink nodes, sand in sink only grows, so it isn't a part of configuration
+    pub fn configuration(&self) -> Vec<i32> {
+        let graph = &self.model.graph;
+        graph.non_sink_nodes().map(|node_idx| graph.nodes[node_idx].sand.get()).collect()
+    }
+
+    // Does parallel steps until some configuration repeats, None if it didn't happen in max_steps steps.
+    // Every visited configuration is kept, so memory is max_steps * nodes count.
+    // Controller is left in the configuration where the repetition was found.
+    pub fn find_parallel_period(&mut self, max_steps: usize) -> Option<PeriodReport> {
+        let mut visited: HashMap<Vec<i32>, usize> = HashMap::new();
+        visited.insert(self.configuration(), 0);
+
+        for step in 1..=max_steps {
+            self.parallel_step();
+            let configuration = self.configuration();
+            if let Some(first_step) = visited.get(&configuration) {
+                return Some(PeriodReport { transient: *first_step, period: step - first_step });
+            }
+            visited.insert(configuration, step);
+        }
+        None
+    }
+}
@@ -0,0 +1,57 @@
+use model::SandPileModel;
+use model::sand_graph::{NodeIndex, SandGraph};
+use super::aggregation::{Aggregation, WalkRule};
+
+// Rotor-router aggregation: every node has a rotor pointing to one of its outgoing edges,
+// walker on an occupied node turns the rotor to the next edge and moves along it
+pub type RotorRouter = Aggregation<Rotors>;
+
+pub struct Rotors {
+    // position of rotor in 0..degree, edge of weight w takes w consecutive positions
+    pub rotors: Vec<i32>,
+}
+
+impl Rotors {
+    pub fn target(&self, graph: &SandGraph, node_idx: NodeIndex) -> NodeIndex {
+        let mut position = self.rotors[node_idx];
+        for (weight, neighbour_node_idx) in graph.successors(node_idx) {
+            if position < weight {
+                return neighbour_node_idx;
+            }
+            position -= weight;
+        }
+        SandGraph::SINK_NODE
+    }
+}
+
+impl WalkRule for Rotors {
+    fn next_node(&mut self, graph: &SandGraph, node_idx: NodeIndex) -> NodeIndex {
+        self.rotors[node_idx] = (self.rotors[node_idx] + 1) % graph.nodes[node_idx].degree;
+        self.target(graph, node_idx)
+    }
+
+    fn clear(&mut self) {
+        for rotor in self.rotors.iter_mut() {
+            *rotor = 0;
+        }
+    }
+}
+
+impl Aggregation<Rotors> {
+    pub fn new(model: SandPileModel) -> Self {
+        let rotors = Rotors { rotors: vec![0; model.graph.nodes.len()] };
+        Aggregation::with_rule(model, rotors)
+    }
+
+    pub fn rotor_target(&self, node_idx: NodeIndex) -> NodeIndex {
+        self.rule.target(&self.model.graph, node_idx)
+    }
+
+    // (node, node its rotor points to) for all nodes with outgoing edges, rotors pointing to sink are included
+    pub fn rotor_arrows(&self) -> Vec<(NodeIndex, NodeIndex)> {
+        self.model.graph.non_sink_nodes()
+            .filter(|node_idx| self.model.graph.nodes[*node_idx].degree > 0)
+            .map(|node_idx| (node_idx, self.rotor_target(node_idx)))
+            .collect()
+    }
+}
@@ -0,0 +1,79 @@
+use model::sand_graph::{NodeIndex, SandGraph};
+use graphics::math;
+use super::SandPileController;
+
+// Toppling waves: avalanche started at source is split into waves, every wave topples source once
+// and then relaxes all other nodes while source is held. On symmetric graphs every node topples
+// at most once in a wave. Grains are always sent by weights, stochastic toppling rule is ignored here.
+impl<'a> SandPileController<'a> {
+    // Adds sand_count grains to source and stabilizes, returns nodes which toppled in every wave
+    // in order of topplings, source is the first node of each wave.
+    // Other unstable nodes are relaxed before the addition, so waves belong to this addition only.
+    // Sink never topples, so there are no waves from it and nothing is done.
+    pub fn add_sand_with_waves(&mut self, source: NodeIndex, sand_count: i32) -> Vec<Vec<NodeIndex>> {
+        if source == SandGraph::SINK_NODE {
+            return Vec::new();
+        }
+        self.relax_except(SandGraph::SINK_NODE);
+
+        let sand = &self.model.graph.nodes[source].sand;
+        sand.set(sand.get() + sand_count);
+
+        let mut waves = Vec::new();
+        while self.model.graph.nodes[source].sand.get() >= self.node_topplings[source].threshold {
+            let mut wave = vec![source];
+            self.topple_by_weights(source);
+            wave.append(&mut self.relax_except(source));
+            waves.push(wave);
+        }
+
+        self.need_update_stack = true;
+        waves
+    }
+
+    pub fn add_sand_by_coords_with_waves(&mut self, coords: math::Vec3d<f32>, sand_count: i32) -> Vec<Vec<NodeIndex>> {
+        let source = self.model.embedding.get_node_by_coords(coords);
+        self.add_sand_with_waves(source, sand_count)
+    }
+
+    fn topple_by_weights(&mut self, node_idx: NodeIndex) {
+        let mut received = vec![0; self.model.graph.nodes.len()];
+        self.send_grains_by_weights(node_idx, &mut received);
+        let graph = &self.model.graph;
+        for (_, neighbour_node_idx) in graph.successors(node_idx) {
+            let sand = &graph.nodes[neighbour_node_idx].sand;
+            sand.set(sand.get() + received[neighbour_node_idx]);
+            received[neighbour_node_idx] = 0;
+        }
+        self.flush_sink(&mut received);
+    }
+
+    // Topples all unstable nodes except held one, returns toppled nodes
+    pub(super) fn relax_except(&mut self, held_node_idx: NodeIndex) -> Vec<NodeIndex> {
+        self.update_stack();
+        let mut toppled = Vec::new();
+
+        while let Some(node_idx) = self.stack.pop() {
+            self.is_in_stack[node_idx] = false;
+            let threshold = self.node_topplings[node_idx].threshold;
+            if node_idx == held_node_idx || self.model.graph.nodes[node_idx].sand.get() < threshold {
+                continue;
+            }
+
+            self.topple_by_weights(node_idx);
+            toppled.push(node_idx);
+
+            let graph = &self.model.graph;
+            for (_, neighbour_node_idx) in graph.successors(node_idx).chain(Some((0, node_idx))) {
+                if neighbour_node_idx != held_node_idx {
+                    SandPileController::add_node_to_stack_if_needed(&mut self.stack, &mut self.is_in_stack,
+                                                                     &graph.nodes[neighbour_node_idx],
+                                                                     self.node_topplings[neighbour_node_idx].threshold,
+                                                                     neighbour_node_idx);
+                }
+            }
+        }
+
+        toppled
+    }
+}
@@ -11,6 +11,10 @@ extern crate shader_version;
 extern crate opengl_graphics;
 extern crate gfx_device_gl;
 extern crate regex;
+extern crate rand;
+extern crate num_bigint;
+extern crate num_traits;
+extern crate num_integer;
 
 pub mod model;
 pub mod view;
@@ -13,9 +13,12 @@ use piston::input::*;
 use glutin_window::GlutinWindow;
 use opengl_graphics::{ GlGraphics, OpenGL };
 
-use sandsim::{model::SandPileModel, view::SandPileView, controller::SandPileController};
+use sandsim::{model::SandPileModel, view::SandPileView, controller::{SandPileController, TopplingRule, NodeToppling}};
 use sandsim::model::{lattice::{SquareLattice, HexagonLattice, TriangleLattice, CubeLattice,
-                               SemiRegularLattice, KUniformLattice, TetrahedralOctahedral},
+                               SemiRegularLattice, KUniformLattice, TetrahedralOctahedral,
+                               HypercubicLattice, SierpinskiGasket, SierpinskiCarpet,
+                               TreeLattice, RecipeLattice, TilingGraph, RandomLattice,
+                               TilingOptions, Neighbourhood, TorusLattice, DirectedSquareLattice},
                      region::{Rectangle, Circle, Parallelepiped, Hexagon, Sphere}};
 use sandsim::view::camera::{ OrbitZoomCamera, OrbitZoomCameraSettings, FirstPerson, FirstPersonSettings, Camera2d };
 use piston_window::{PistonWindow, OpenGLWindow, AdvancedWindow};
@@ -40,10 +43,20 @@ fn main() {
         .ups(1000)
         .max_fps(60));
 
-    // let lattice = SemiRegularLattice::new(vec![3, 4, 6, 4]);
+    // let lattice = SemiRegularLattice::new(vec![3, 4, 6, 4]).unwrap();
     // let lattice = SquareLattice::new();
     // let lattice = HexagonLattice::new();
+    // let lattice = HexagonLattice::new().with_graph(TilingGraph::Vertices);
+    // let lattice = SquareLattice::new().with_options(TilingOptions { neighbourhood: Neighbourhood::Moore, ..TilingOptions::new() });
     // let lattice = TetrahedralOctahedral::new();
+    // let lattice = HypercubicLattice::new(4, 3);
+    // let lattice = TorusLattice::new();
+    // let lattice = DirectedSquareLattice::dhar_ramaswamy();
+    // let lattice = SierpinskiGasket::new(6);
+    // let lattice = SierpinskiCarpet::new(4);
+    // let lattice = TreeLattice::bethe(3, 8);
+    // let lattice = RandomLattice::watts_strogatz(500, 4, 0.1, 42);
+    // let lattice = RecipeLattice::from_file("tilings/3_6-3_3.4_2.tiling").unwrap();
     let lattice = KUniformLattice::new();
 
     // let region = Parallelepiped::new(200.0, 200.0, 100.0);
@@ -78,13 +91,19 @@ fn main() {
     let camera = orbital;
 
     let mut model = SandPileModel::new(region, lattice);
+    // let mut model = sandsim::model::import::load_graph("graph.dot", "sink").unwrap();
     // model.transpose();
+    // model.randomize_weights(sandsim::model::WeightDistribution::Uniform { min: 1, max: 3 }, true, 42).unwrap();
     // let f1 = |_, d| { 2*d };
     // let f2 = |s, d| { 2*d - s};
 
     let mut controller = SandPileController::new(model);
 
     // controller.set_pipeline(vec![&f1, &f2]);
+    // controller.set_toppling_rule(TopplingRule::Stochastic { seed: 42 });
+    // controller.set_node_topplings(&|_, d| NodeToppling::dissipative(d, 1)).unwrap();
+    // controller.set_grain_orbit_by_coords([0.5*side, 0.5*side, 0.0]);
+    // controller.random_recurrent(42).unwrap();
     let mut view =
         SandPileView::new(  &mut window.factory, &controller.model, opengl, camera);
 
@@ -138,6 +157,8 @@ fn main() {
                 // view.draw_tiling(&mut window, args, &controller.model);
                 view.draw_borders(&mut window, args, &controller.model);
                 view.draw_graph(&mut window, args, &controller.model);
+                // let tree = controller.tree_from_recurrent(&controller.configuration()).unwrap();
+                // view.draw_tree(&mut window, args, &controller.model, &tree);
             }
 
 
@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use super::SandPileModel;
+use super::sand_graph::{SandGraph, NodeIndex};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WeightDistribution {
+    // every integer in min..=max with equal probability
+    Uniform { min: i32, max: i32 },
+    // high with probability p, low otherwise
+    Bimodal { low: i32, high: i32, p: f64 },
+}
+
+impl WeightDistribution {
+    fn sample<R: Rng>(&self, rng: &mut R) -> i32 {
+        match *self {
+            WeightDistribution::Uniform { min, max } => rng.gen_range(min, max + 1),
+            WeightDistribution::Bimodal { low, high, p } => if rng.gen_bool(p) { high } else { low },
+        }
+    }
+
+    fn check(&self) -> Result<(), String> {
+        let (min, max) = match *self {
+            WeightDistribution::Uniform { min, max } => (min, max),
+            WeightDistribution::Bimodal { low, high, p } => {
+                if ! (0.0 <= p && p <= 1.0) {
+                    return Err(format!("{:?}: probability have to be in [0, 1]", self));
+                }
+                (low.min(high), low.max(high))
+            },
+        };
+        if min < 1 || min > max {
+            return Err(format!("{:?}: weights have to be positive and min can't be greater than max", self));
+        }
+        Ok(())
+    }
+}
+
+impl SandPileModel {
+    // Multiplies weight of every edge between non-sink nodes by a random factor from distribution,
+    // edges to sink are kept, so the boundary stays the same. With symmetric weights u -> v and v -> u
+    // get the same factor (random conductances), otherwise every direction is drawn independently.
+    // Controller keeps thresholds from degrees, so use SandPileController::randomize_weights once it is created.
+    pub fn randomize_weights(&mut self, distribution: WeightDistribution, symmetric: bool, seed: u64) -> Result<(), String> {
+        distribution.check()?;
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut factors: HashMap<(NodeIndex, NodeIndex), i32> = HashMap::new();
+
+        self.graph.map_edge_weights(&mut |source, target, weight| {
+            if target == SandGraph::SINK_NODE {
+                return weight;
+            }
+            let factor = if symmetric {
+                let key = (source.min(target), source.max(target));
+                *factors.entry(key).or_insert_with(|| distribution.sample(&mut rng))
+            } else {
+                distribution.sample(&mut rng)
+            };
+            weight * factor
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,344 @@
+use std::collections::HashMap;
+use super::sand_graph::{SandGraph, NodeIndex};
+
+// Baker-Norine divisor theory on the graph of non-sink nodes, edges to sink are dropped.
+// Edge weights are edge multiplicities, the graph has to be undirected and connected.
+// Divisors are indexed by node index, so chips[SandGraph::SINK_NODE] is unused and always 0.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Divisor {
+    pub chips: Vec<i64>,
+}
+
+pub struct DivisorGraph {
+    // (neighbour, multiplicity) without loops
+    adjacency: Vec<Vec<(NodeIndex, i64)>>,
+    degrees: Vec<i64>,
+}
+
+impl Divisor {
+    pub fn zero(nodes_count: usize) -> Self {
+        Divisor { chips: vec![0; nodes_count] }
+    }
+
+    pub fn from_configuration(graph: &SandGraph) -> Self {
+        let mut divisor = Divisor::zero(graph.nodes.len());
+        for node_idx in graph.non_sink_nodes() {
+            divisor.chips[node_idx] = graph.nodes[node_idx].sand.get() as i64;
+        }
+        divisor
+    }
+
+    pub fn degree(&self) -> i64 {
+        self.chips.iter().sum()
+    }
+
+    pub fn is_effective(&self) -> bool {
+        self.chips.iter().all(|c| *c >= 0)
+    }
+
+    pub fn add(&self, other: &Divisor) -> Divisor {
+        assert_eq!(self.chips.len(), other.chips.len(), "divisors are on graphs with different numbers of nodes");
+        Divisor { chips: self.chips.iter().zip(&other.chips).map(|(a, b)| a + b).collect() }
+    }
+
+    pub fn sub(&self, other: &Divisor) -> Divisor {
+        assert_eq!(self.chips.len(), other.chips.len(), "divisors are on graphs with different numbers of nodes");
+        Divisor { chips: self.chips.iter().zip(&other.chips).map(|(a, b)| a - b).collect() }
+    }
+}
+
+impl DivisorGraph {
+    pub fn new(graph: &SandGraph) -> Result<Self, String> {
+        let nodes_count = graph.nodes.len();
+        let mut adjacency: Vec<Vec<(NodeIndex, i64)>> = vec![Vec::new(); nodes_count];
+        let mut weights: Vec<HashMap<NodeIndex, i64>> = vec![HashMap::new(); nodes_count];
+
+        for node_idx in graph.non_sink_nodes() {
+            for (weight, neighbour_node_idx) in graph.successors(node_idx) {
+                if neighbour_node_idx != SandGraph::SINK_NODE && neighbour_node_idx != node_idx {
+                    *weights[node_idx].entry(neighbour_node_idx).or_insert(0) += weight as i64;
+                }
+            }
+        }
+        for node_idx in graph.non_sink_nodes() {
+            for (neighbour_node_idx, weight) in &weights[node_idx] {
+                if weights[*neighbour_node_idx].get(&node_idx) != Some(weight) {
+                    return Err(format!("graph is directed, edge {} -> {} has weight {}, but reversed edge has weight {}",
+                                       node_idx, neighbour_node_idx, weight,
+                                       weights[*neighbour_node_idx].get(&node_idx).unwrap_or(&0)));
+                }
+                adjacency[node_idx].push((*neighbour_node_idx, *weight));
+            }
+            adjacency[node_idx].sort();
+        }
+
+        let degrees = adjacency.iter().map(|neighbours| neighbours.iter().map(|(_, w)| w).sum()).collect();
+        let divisor_graph = DivisorGraph { adjacency, degrees };
+        if ! divisor_graph.is_connected() {
+            return Err("graph without sink is not connected".to_string());
+        }
+        Ok(divisor_graph)
+    }
+
+    fn is_connected(&self) -> bool {
+        let nodes_count = self.adjacency.len();
+        if nodes_count <= 2 {
+            return true;
+        }
+        let mut is_visited = vec![false; nodes_count];
+        let mut stack = vec![1];
+        is_visited[1] = true;
+        while let Some(node_idx) = stack.pop() {
+            for (neighbour_node_idx, _) in &self.adjacency[node_idx] {
+                if ! is_visited[*neighbour_node_idx] {
+                    is_visited[*neighbour_node_idx] = true;
+                    stack.push(*neighbour_node_idx);
+                }
+            }
+        }
+        is_visited.iter().skip(1).all(|v| *v)
+    }
+
+    fn nodes(&self) -> std::ops::Range<NodeIndex> {
+        1..self.adjacency.len()
+    }
+
+    // g = |E| - |V| + 1
+    pub fn genus(&self) -> i64 {
+        let edges_count: i64 = self.degrees.iter().sum::<i64>() / 2;
+        edges_count - self.nodes().len() as i64 + 1
+    }
+
+    // K(v) = deg(v) - 2
+    pub fn canonical(&self) -> Divisor {
+        let mut divisor = Divisor::zero(self.adjacency.len());
+        for node_idx in self.nodes() {
+            divisor.chips[node_idx] = self.degrees[node_idx] - 2;
+        }
+        divisor
+    }
+
+    pub fn fire(&self, divisor: &mut Divisor, node_idx: NodeIndex, times: i64) {
+        divisor.chips[node_idx] -= self.degrees[node_idx] * times;
+        for (neighbour_node_idx, weight) in &self.adjacency[node_idx] {
+            divisor.chips[*neighbour_node_idx] += weight * times;
+        }
+    }
+
+    // Fires every node of the set once, chips move only along edges leaving the set
+    fn fire_set(&self, divisor: &mut Divisor, is_in_set: &Vec<bool>) {
+        for node_idx in self.nodes() {
+            if ! is_in_set[node_idx] {
+                continue;
+            }
+            for (neighbour_node_idx, weight) in &self.adjacency[node_idx] {
+                if ! is_in_set[*neighbour_node_idx] {
+                    divisor.chips[node_idx] -= weight;
+                    divisor.chips[*neighbour_node_idx] += weight;
+                }
+            }
+        }
+    }
+
+    // The unique q-reduced divisor linearly equivalent to divisor:
+    // it is effective outside q and no set of nodes without q can fire without some node going negative
+    pub fn reduce(&self, divisor: &Divisor, q: NodeIndex) -> Divisor {
+        let mut divisor = divisor.clone();
+
+        // make divisor effective outside q: q fires and other nodes stabilize with q as a sink.
+        // Node which fired at least once is not negative, so it ends when every node has fired
+        loop {
+            let deficit = self.nodes()
+                .filter(|node_idx| *node_idx != q)
+                .map(|node_idx| -divisor.chips[node_idx])
+                .max()
+                .unwrap_or(0);
+            if deficit <= 0 {
+                break;
+            }
+            self.fire(&mut divisor, q, deficit);
+            self.stabilize_except(&mut divisor, q);
+        }
+
+        // Dhar's burning algorithm: fire set of unburnt nodes while it is not empty
+        loop {
+            let is_unburnt = self.dhar_burning(&divisor, q);
+            if ! is_unburnt.iter().any(|u| *u) {
+                break;
+            }
+            self.fire_set(&mut divisor, &is_unburnt);
+        }
+
+        divisor
+    }
+
+    fn stabilize_except(&self, divisor: &mut Divisor, q: NodeIndex) {
+        let mut stack: Vec<NodeIndex> = self.nodes().collect();
+        while let Some(node_idx) = stack.pop() {
+            if node_idx == q || self.degrees[node_idx] == 0 || divisor.chips[node_idx] < self.degrees[node_idx] {
+                continue;
+            }
+            let times = divisor.chips[node_idx] / self.degrees[node_idx];
+            self.fire(divisor, node_idx, times);
+            for (neighbour_node_idx, _) in &self.adjacency[node_idx] {
+                stack.push(*neighbour_node_idx);
+            }
+        }
+    }
+
+    // Fire starts at q, node burns when it has less chips than edges to burnt nodes.
+    // Returns unburnt nodes, which can fire together
+    pub fn dhar_burning(&self, divisor: &Divisor, q: NodeIndex) -> Vec<bool> {
+        let mut is_unburnt: Vec<bool> = (0..self.adjacency.len()).map(|node_idx| node_idx != SandGraph::SINK_NODE).collect();
+        let mut burnt_edges = vec![0; self.adjacency.len()];
+        is_unburnt[q] = false;
+        let mut stack = vec![q];
+
+        while let Some(node_idx) = stack.pop() {
+            for (neighbour_node_idx, weight) in &self.adjacency[node_idx] {
+                if ! is_unburnt[*neighbour_node_idx] {
+                    continue;
+                }
+                burnt_edges[*neighbour_node_idx] += weight;
+                if divisor.chips[*neighbour_node_idx] < burnt_edges[*neighbour_node_idx] {
+                    is_unburnt[*neighbour_node_idx] = false;
+                    stack.push(*neighbour_node_idx);
+                }
+            }
+        }
+        is_unburnt
+    }
+
+    pub fn is_linearly_equivalent(&self, a: &Divisor, b: &Divisor) -> bool {
+        self.reduce(a, 1) == self.reduce(b, 1)
+    }
+
+    // |D| is not empty
+    pub fn has_effective_equivalent(&self, divisor: &Divisor) -> bool {
+        self.reduce(divisor, 1).is_effective()
+    }
+
+    // Largest r such that D - E has effective equivalent for every effective E of degree r, -1 if D has none.
+    // Goes over all effective E, so it is meant for small graphs and degrees
+    pub fn rank(&self, divisor: &Divisor) -> i64 {
+        if ! self.has_effective_equivalent(divisor) {
+            return -1;
+        }
+        let nodes: Vec<NodeIndex> = self.nodes().collect();
+        let mut r = 0;
+        loop {
+            let mut e = Divisor::zero(self.adjacency.len());
+            if ! self.all_subtractions_effective(divisor, &mut e, &nodes, 0, r + 1) {
+                return r;
+            }
+            r += 1;
+        }
+    }
+
+    // E gets chips_left more chips on nodes[first..], checks D - E for every such E
+    fn all_subtractions_effective(&self, divisor: &Divisor, e: &mut Divisor, nodes: &Vec<NodeIndex>,
+                                  first: usize, chips_left: i64) -> bool {
+        if chips_left == 0 {
+            return self.has_effective_equivalent(&divisor.sub(e));
+        }
+        for i in first..nodes.len() {
+            e.chips[nodes[i]] += 1;
+            let result = self.all_subtractions_effective(divisor, e, nodes, i, chips_left - 1);
+            e.chips[nodes[i]] -= 1;
+            if ! result {
+                return false;
+            }
+        }
+        true
+    }
+
+    // r(D) - r(K - D) = deg(D) + 1 - g
+    pub fn check_riemann_roch(&self, divisor: &Divisor) -> Result<(), String> {
+        let rank = self.rank(divisor);
+        let dual_rank = self.rank(&self.canonical().sub(divisor));
+        let expected = divisor.degree() + 1 - self.genus();
+        if rank - dual_rank != expected {
+            return Err(format!("r(D) = {}, r(K - D) = {}, but deg(D) + 1 - g = {}", rank, dual_rank, expected));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use model::sand_graph::SandGraph;
+    use super::{Divisor, DivisorGraph};
+
+    // complete graph on nodes 1..=n, every edge in both directions
+    fn complete_graph(n: usize) -> SandGraph {
+        let mut graph = SandGraph::new();
+        for _ in 0..n {
+            graph.add_node();
+        }
+        for u in graph.non_sink_nodes() {
+            for v in graph.non_sink_nodes() {
+                if u != v {
+                    graph.add_edge(u, v, 1);
+                }
+            }
+        }
+        graph
+    }
+
+    fn divisor(chips: Vec<i64>) -> Divisor {
+        let mut divisor = Divisor::zero(chips.len() + 1);
+        divisor.chips[1..].copy_from_slice(&chips);
+        divisor
+    }
+
+    #[test]
+    fn k4_genus_and_canonical() {
+        let divisor_graph = DivisorGraph::new(&complete_graph(4)).unwrap();
+        assert_eq!(divisor_graph.genus(), 3);
+        assert_eq!(divisor_graph.canonical(), divisor(vec![1, 1, 1, 1]));
+    }
+
+    #[test]
+    fn k4_ranks() {
+        let divisor_graph = DivisorGraph::new(&complete_graph(4)).unwrap();
+        assert_eq!(divisor_graph.rank(&divisor(vec![0, 0, 0, 0])), 0);
+        assert_eq!(divisor_graph.rank(&divisor(vec![-1, 0, 0, 0])), -1);
+        // v - w has order 4 in the sandpile group Z4 x Z4 of K4, so it is not equivalent to zero
+        assert_eq!(divisor_graph.rank(&divisor(vec![1, -1, 0, 0])), -1);
+        assert_eq!(divisor_graph.rank(&divisor(vec![1, 0, 0, 0])), 0);
+        // r(K) = g - 1
+        assert_eq!(divisor_graph.rank(&divisor_graph.canonical()), 2);
+        // deg(D) > 2g - 2 gives r(D) = deg(D) - g
+        assert_eq!(divisor_graph.rank(&divisor(vec![2, 1, 1, 1])), 2);
+    }
+
+    #[test]
+    fn k4_riemann_roch() {
+        let divisor_graph = DivisorGraph::new(&complete_graph(4)).unwrap();
+        for chips in &[vec![0, 0, 0, 0], vec![1, 0, 0, 0], vec![2, -1, 0, 1], vec![3, 0, 0, 0], vec![1, 1, 1, 1], vec![2, 2, 1, 0]] {
+            divisor_graph.check_riemann_roch(&divisor(chips.clone())).unwrap();
+        }
+    }
+
+    #[test]
+    fn reduce_doesnt_depend_on_firing() {
+        let divisor_graph = DivisorGraph::new(&complete_graph(4)).unwrap();
+        let d = divisor(vec![3, -2, 0, 1]);
+        let mut fired = d.clone();
+        divisor_graph.fire(&mut fired, 2, 3);
+        divisor_graph.fire(&mut fired, 4, -1);
+        assert_eq!(divisor_graph.reduce(&d, 1), divisor_graph.reduce(&fired, 1));
+        assert!(divisor_graph.is_linearly_equivalent(&d, &fired));
+
+        // q-reduced divisor is effective outside q and its unburnt set is empty
+        let reduced = divisor_graph.reduce(&d, 1);
+        assert!(reduced.chips[2..].iter().all(|c| *c >= 0));
+        assert!(! divisor_graph.dhar_burning(&reduced, 1).iter().any(|u| *u));
+    }
+
+    #[test]
+    #[should_panic]
+    fn add_checks_lengths() {
+        divisor(vec![1, 2]).add(&divisor(vec![1, 2, 3]));
+    }
+}
@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use std::fs;
+use std::path::Path;
+
+use graphics::math;
+use regex::Regex;
+use super::SandPileModel;
+use super::sand_graph::{SandGraph, NodeIndex};
+use super::embedding::{EmbeddingToR3, Figure};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GraphFormat {
+    // "u v [weight]" per line, undirected, '#' and '%' start comments
+    EdgeList,
+    // graph / digraph with "--" / "->" edges, "weight" and "pos" attributes
+    Dot,
+    // <node> / <edge> with <data> for keys named "weight", "x", "y", "z"
+    GraphMl,
+}
+
+// Graph as it is written in file, before sink vertex is replaced by SandGraph::SINK_NODE
+struct ImportedGraph {
+    ids: Vec<String>,
+    id_to_idx: HashMap<String, usize>,
+    coords: Vec<Option<math::Vec3d<f32>>>,
+    edges: Vec<(usize, usize, i32)>,
+}
+
+impl GraphFormat {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "txt" | "edges" | "edgelist" => Some(GraphFormat::EdgeList),
+            "dot" | "gv" => Some(GraphFormat::Dot),
+            "graphml" | "xml" => Some(GraphFormat::GraphMl),
+            _ => None,
+        }
+    }
+}
+
+// Format is guessed by file extension
+pub fn load_graph<P: AsRef<Path>>(path: P, sink_id: &str) -> Result<SandPileModel, String> {
+    let format = GraphFormat::from_path(&path)
+        .ok_or(format!("can't guess graph format of {}", path.as_ref().display()))?;
+    let text = fs::read_to_string(&path)
+        .map_err(|e| format!("can't read {}: {}", path.as_ref().display(), e))?;
+    parse_graph(&text, format, sink_id)
+}
+
+// Every edge to sink_id vertex goes to SandGraph::SINK_NODE, edges from it are dropped.
+// Node coordinates are used only if all nodes have them, otherwise nodes are placed on a circle.
+pub fn parse_graph(text: &str, format: GraphFormat, sink_id: &str) -> Result<SandPileModel, String> {
+    let graph = match format {
+        GraphFormat::EdgeList => ImportedGraph::parse_edge_list(text)?,
+        GraphFormat::Dot => ImportedGraph::parse_dot(text)?,
+        GraphFormat::GraphMl => ImportedGraph::parse_graphml(text)?,
+    };
+    graph.into_model(sink_id)
+}
+
+fn parse_weight(value: &str) -> Result<i32, String> {
+    let weight = value.trim().parse::<i32>().map_err(|_| format!("edge weight has to be integer, got '{}'", value))?;
+    if weight <= 0 {
+        return Err(format!("edge weight has to be positive, got {}", weight));
+    }
+    Ok(weight)
+}
+
+fn parse_coords(values: &[&str]) -> Result<math::Vec3d<f32>, String> {
+    let mut coords = [0.0; 3];
+    for (i, value) in values.iter().take(3).enumerate() {
+        coords[i] = value.trim().parse::<f32>().map_err(|_| format!("incorrect coordinate '{}'", value))?;
+    }
+    Ok(coords)
+}
+
+impl ImportedGraph {
+    fn new() -> Self {
+        ImportedGraph { ids: Vec::new(), id_to_idx: HashMap::new(), coords: Vec::new(), edges: Vec::new() }
+    }
+
+    fn node(&mut self, id: &str) -> usize {
+        if let Some(idx) = self.id_to_idx.get(id) {
+            return *idx;
+        }
+        let idx = self.ids.len();
+        self.ids.push(id.to_string());
+        self.coords.push(None);
+        self.id_to_idx.insert(id.to_string(), idx);
+        idx
+    }
+
+    fn add_edge(&mut self, source: &str, target: &str, weight: i32, directed: bool) {
+        let source = self.node(source);
+        let target = self.node(target);
+        self.edges.push((source, target, weight));
+        if ! directed && source != target {
+            self.edges.push((target, source, weight));
+        }
+    }
+
+    fn parse_edge_list(text: &str) -> Result<Self, String> {
+        let mut graph = Self::new();
+
+        for (line_idx, line) in text.lines().enumerate() {
+            let line = line.split(|c| c == '#' || c == '%').next().unwrap();
+            let words: Vec<&str> = line.split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|w| ! w.is_empty())
+                .collect();
+
+            match words.len() {
+                0 => continue,
+                1 => { graph.node(words[0]); },
+                2 => graph.add_edge(words[0], words[1], 1, false),
+                3 => {
+                    let weight = parse_weight(words[2]).map_err(|e| format!("line {}: {}", line_idx + 1, e))?;
+                    graph.add_edge(words[0], words[1], weight, false);
+                },
+                _ => return Err(format!("line {}: expected 'u v [weight]'", line_idx + 1)),
+            }
+        }
+
+        Ok(graph)
+    }
+
+    fn dot_tokens(text: &str) -> Vec<String> {
+        let comments = Regex::new(r"(?s)/\*.*?\*/|//[^\n]*|(?m)^\s*#[^\n]*").unwrap();
+        let token = Regex::new(r#""(?:[^"\\]|\\.)*"|--|->|[{}\[\]=;,:]|-?[0-9]*\.?[0-9]+|[A-Za-z_\x80-\xff][A-Za-z0-9_\x80-\xff]*"#).unwrap();
+        let text = comments.replace_all(text, " ");
+
+        token.find_iter(&text)
+            .map(|m| {
+                let s = m.as_str();
+                if s.starts_with('"') {
+                    s[1..s.len() - 1].replace("\\\"", "\"")
+                } else {
+                    s.to_string()
+                }
+            })
+            .collect()
+    }
+
+    fn parse_dot(text: &str) -> Result<Self, String> {
+        let tokens = Self::dot_tokens(text);
+        let mut graph = Self::new();
+
+        let mut pos = 0;
+        if tokens.get(pos).map(|t| t.to_lowercase()) == Some("strict".to_string()) {
+            pos += 1;
+        }
+        let directed = match tokens.get(pos).map(|t| t.to_lowercase()) {
+            Some(ref t) if t == "graph" => false,
+            Some(ref t) if t == "digraph" => true,
+            _ => return Err("DOT file has to start with 'graph' or 'digraph'".to_string()),
+        };
+        pos += 1;
+        if tokens.get(pos).map(|t| t.as_str()) != Some("{") {
+            pos += 1;
+        }
+        if tokens.get(pos).map(|t| t.as_str()) != Some("{") {
+            return Err("expected '{' after graph header".to_string());
+        }
+        pos += 1;
+
+        while pos < tokens.len() && tokens[pos] != "}" {
+            let token = tokens[pos].as_str();
+            if token == ";" || token == "," {
+                pos += 1;
+                continue;
+            }
+            if token == "{" || token == "subgraph" {
+                return Err("subgraphs are not supported".to_string());
+            }
+
+            // statement: id (edge_op id)* [attributes]
+            let mut ids: Vec<String> = vec![tokens[pos].clone()];
+            pos += 1;
+            if pos < tokens.len() && tokens[pos] == "=" {
+                // graph attribute, id = id
+                pos += 2;
+                continue;
+            }
+            while pos + 1 < tokens.len() && (tokens[pos] == "--" || tokens[pos] == "->") {
+                ids.push(tokens[pos + 1].clone());
+                pos += 2;
+            }
+
+            let mut attributes: HashMap<String, String> = HashMap::new();
+            while pos < tokens.len() && tokens[pos] == "[" {
+                pos += 1;
+                while pos < tokens.len() && tokens[pos] != "]" {
+                    if tokens[pos] == ";" || tokens[pos] == "," {
+                        pos += 1;
+                        continue;
+                    }
+                    if pos + 2 < tokens.len() && tokens[pos + 1] == "=" {
+                        attributes.insert(tokens[pos].to_lowercase(), tokens[pos + 2].clone());
+                        pos += 3;
+                    } else {
+                        pos += 1;
+                    }
+                }
+                pos += 1;
+            }
+
+            let keyword = ids[0].to_lowercase();
+            if ids.len() == 1 && (keyword == "graph" || keyword == "node" || keyword == "edge") {
+                continue;
+            }
+
+            if ids.len() == 1 {
+                let idx = graph.node(&ids[0]);
+                if let Some(position) = attributes.get("pos") {
+                    let values: Vec<&str> = position.trim_end_matches('!').split(',').collect();
+                    graph.coords[idx] = Some(parse_coords(&values)?);
+                }
+            } else {
+                let weight = match attributes.get("weight") {
+                    Some(value) => parse_weight(value)?,
+                    None => 1,
+                };
+                for i in 0..ids.len() - 1 {
+                    graph.add_edge(&ids[i], &ids[i + 1], weight, directed);
+                }
+            }
+        }
+
+        if pos >= tokens.len() {
+            return Err("expected '}' at the end of graph".to_string());
+        }
+
+        Ok(graph)
+    }
+
+    fn xml_attributes(tag: &str) -> HashMap<String, String> {
+        let attribute = Regex::new(r#"([A-Za-z_][\w.:-]*)\s*=\s*"([^"]*)""#).unwrap();
+        attribute.captures_iter(tag)
+            .map(|c| (c[1].to_string(), c[2].to_string()))
+            .collect()
+    }
+
+    fn parse_graphml(text: &str) -> Result<Self, String> {
+        let key_re = Regex::new(r"<key\b([^>]*?)/?>").unwrap();
+        let graph_re = Regex::new(r"<graph\b([^>]*)>").unwrap();
+        let element_re = Regex::new(r"(?s)<(node|edge)\b([^>]*?)(?:/>|>(.*?)</(?:node|edge)>)").unwrap();
+        let data_re = Regex::new(r"(?s)<data\b([^>]*)>(.*?)</data>").unwrap();
+
+        // key id -> attribute name, e.g. "d0" -> "weight"
+        let mut keys: HashMap<String, String> = HashMap::new();
+        for captures in key_re.captures_iter(text) {
+            let attributes = Self::xml_attributes(&captures[1]);
+            if let Some(id) = attributes.get("id") {
+                let name = attributes.get("attr.name").unwrap_or(id);
+                keys.insert(id.clone(), name.to_lowercase());
+            }
+        }
+
+        let graph_attributes = graph_re.captures(text)
+            .map(|c| Self::xml_attributes(&c[1]))
+            .ok_or("GraphML file has no <graph> element".to_string())?;
+        let default_directed = graph_attributes.get("edgedefault").map(|s| s.as_str()) == Some("directed");
+
+        let mut graph = Self::new();
+        for captures in element_re.captures_iter(text) {
+            let attributes = Self::xml_attributes(&captures[2]);
+            let mut data: HashMap<String, String> = HashMap::new();
+            if let Some(body) = captures.get(3) {
+                for data_captures in data_re.captures_iter(body.as_str()) {
+                    let data_attributes = Self::xml_attributes(&data_captures[1]);
+                    if let Some(key) = data_attributes.get("key") {
+                        let name = keys.get(key).unwrap_or(key).clone();
+                        data.insert(name, data_captures[2].trim().to_string());
+                    }
+                }
+            }
+
+            if &captures[1] == "node" {
+                let id = attributes.get("id").ok_or("<node> without id".to_string())?;
+                let idx = graph.node(id);
+                if let (Some(x), Some(y)) = (data.get("x"), data.get("y")) {
+                    let z = data.get("z").map(|z| z.as_str()).unwrap_or("0");
+                    graph.coords[idx] = Some(parse_coords(&[x.as_str(), y.as_str(), z])?);
+                }
+            } else {
+                let source = attributes.get("source").ok_or("<edge> without source".to_string())?;
+                let target = attributes.get("target").ok_or("<edge> without target".to_string())?;
+                let directed = match attributes.get("directed").map(|s| s.as_str()) {
+                    Some("true") => true,
+                    Some("false") => false,
+                    _ => default_directed,
+                };
+                let weight = match data.get("weight") {
+                    Some(value) => parse_weight(value)?,
+                    None => 1,
+                };
+                graph.add_edge(source, target, weight, directed);
+            }
+        }
+
+        Ok(graph)
+    }
+
+    fn into_model(self, sink_id: &str) -> Result<SandPileModel, String> {
+        let sink_idx = *self.id_to_idx.get(sink_id)
+            .ok_or(format!("sink vertex '{}' is not found in graph", sink_id))?;
+
+        let mut sand_graph = SandGraph::new();
+        let mut embedding = EmbeddingToR3::new();
+
+        let mut idx_to_node: Vec<NodeIndex> = vec![SandGraph::SINK_NODE; self.ids.len()];
+        for idx in 0..self.ids.len() {
+            if idx != sink_idx {
+                idx_to_node[idx] = sand_graph.add_node();
+            }
+        }
+
+        for (source, target, weight) in &self.edges {
+            if *source != sink_idx {
+                sand_graph.add_edge(idx_to_node[*source], idx_to_node[*target], *weight);
+            }
+        }
+        sand_graph.check_sink_reachable()
+            .map_err(|_| "some vertices can't reach sink, sand on them would never stabilize".to_string())?;
+
+        let nodes_count = sand_graph.nodes.len() - 1;
+        let has_coords = (0..self.ids.len()).all(|idx| idx == sink_idx || self.coords[idx].is_some());
+        let radius = (nodes_count as f32 / (2.0 * PI)).max(1.0);
+
+        let mut coords: Vec<math::Vec3d<f32>> = vec![[0.0; 3]; sand_graph.nodes.len()];
+        for idx in 0..self.ids.len() {
+            let node_idx = idx_to_node[idx];
+            if node_idx == SandGraph::SINK_NODE {
+                continue;
+            }
+            coords[node_idx] = if has_coords {
+                self.coords[idx].unwrap()
+            } else {
+                let angle = 2.0 * PI * (node_idx - 1) as f32 / nodes_count as f32;
+                [radius * (1.0 + angle.cos()), radius * (1.0 + angle.sin()), 0.0]
+            };
+        }
+
+        // figure size is a fraction of average edge length
+        let mut length_sum = 0.0;
+        let mut edges_count = 0;
+        for node_idx in sand_graph.non_sink_nodes() {
+            for (_, neighbour_idx) in sand_graph.successors(node_idx) {
+                if neighbour_idx != SandGraph::SINK_NODE && neighbour_idx != node_idx {
+                    length_sum += vecmath::vec3_len(vecmath::vec3_sub(coords[node_idx], coords[neighbour_idx]));
+                    edges_count += 1;
+                }
+            }
+        }
+        let figure_radius = if edges_count > 0 { 0.2 * length_sum / edges_count as f32 } else { 0.2 };
+        let figure_idx = embedding.add_figure(Figure::polygon_on_circle(figure_radius, 8, 0.0, 45.0));
+
+        for node_idx in sand_graph.non_sink_nodes() {
+            embedding.set_node_info(node_idx, coords[node_idx], figure_idx);
+        }
+
+        Ok(SandPileModel { graph: sand_graph, embedding })
+    }
+}
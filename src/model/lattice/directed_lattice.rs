@@ -0,0 +1,221 @@
+use super::Lattice;
+
+use model::SandPileModel;
+use model::region::Cuboid;
+use model::sand_graph::SandGraph;
+use model::embedding::{ EmbeddingToR3, Figure };
+
+
+// Lattices where every edge goes one row down. Rows are periodic along x with hull x size nodes,
+// the top row is at the top of the hull and edges from the bottom row go to sink.
+pub struct DirectedSquareLattice {
+    with_vertical_edges: bool,
+}
+
+// Honeycomb with vertical bonds, every bond is directed down:
+// nodes with two bonds below send grains along both of them, nodes with vertical bond below send all along it
+pub struct DirectedHoneycombLattice {}
+
+impl DirectedSquareLattice {
+    // Dhar-Ramaswamy model, square lattice turned by 45 degrees,
+    // every node sends one grain to each of its two lower neighbours
+    pub fn dhar_ramaswamy() -> Self {
+        DirectedSquareLattice { with_vertical_edges: false }
+    }
+
+    // square lattice, every node sends one grain down and one grain to each of two lower diagonal neighbours
+    pub fn with_diagonals() -> Self {
+        DirectedSquareLattice { with_vertical_edges: true }
+    }
+}
+
+impl Lattice for DirectedSquareLattice {
+    fn get_lattice(&self, cuboid_hull: &Cuboid) -> SandPileModel {
+        if self.with_vertical_edges {
+            build_directed_rows(cuboid_hull,
+                                &|_| 0.0,
+                                &|_| 1.0,
+                                &|_| vec![-1.0, 0.0, 1.0],
+                                Figure::polygon_on_circle(0.5_f32.powf(0.5), 4, 45.0, 90.0))
+        } else {
+            build_directed_rows(cuboid_hull,
+                                &|row| 0.5 * (row % 2) as f32,
+                                &|_| 0.5,
+                                &|_| vec![-0.5, 0.5],
+                                Figure::polygon_on_circle(0.5, 4, 0.0, 90.0))
+        }
+    }
+}
+
+impl DirectedHoneycombLattice {
+    pub fn new() -> Self {
+        DirectedHoneycombLattice {}
+    }
+}
+
+impl Lattice for DirectedHoneycombLattice {
+    fn get_lattice(&self, cuboid_hull: &Cuboid) -> SandPileModel {
+        // distance between nodes of a row is 1, so bond length is 1 / sqrt(3)
+        let bond = 1.0 / 3_f32.powf(0.5);
+
+        // rows go A B B A A B B ..., where A nodes are above a pair of diagonal bonds
+        build_directed_rows(cuboid_hull,
+                            &|row| 0.5 * (((row + 1) / 2) % 2) as f32,
+                            &move |row| if row % 2 == 0 { 0.5 * bond } else { bond },
+                            &|row| if row % 2 == 0 { vec![-0.5, 0.5] } else { vec![0.0] },
+                            Figure::polygon_on_circle(0.3 * bond, 8, 0.0, 45.0))
+    }
+}
+
+// row_offset(row) - x of the first node of the row,
+// row_step(row) - distance between the row and the next one,
+// down_shifts(row) - x shifts from a node of the row to its successors in the next row
+fn build_directed_rows(cuboid_hull: &Cuboid,
+                       row_offset: &Fn(usize) -> f32,
+                       row_step: &Fn(usize) -> f32,
+                       down_shifts: &Fn(usize) -> Vec<f32>,
+                       figure: Figure) -> SandPileModel {
+    let [x_size, y_size, _] = *cuboid_hull;
+    let width = (x_size as usize).max(1);
+
+    let mut rows_y: Vec<f32> = vec![y_size];
+    while rows_y[rows_y.len() - 1] - row_step(rows_y.len() - 1) >= 0.0 {
+        let row = rows_y.len() - 1;
+        rows_y.push(rows_y[row] - row_step(row));
+    }
+    let rows_count = rows_y.len();
+
+    let coords_to_index = |i: usize, row: usize| { row*width + i + 1 };
+
+    let mut sand_graph = SandGraph::new();
+    let mut embedding = EmbeddingToR3::new();
+    embedding.add_figure(figure);
+
+    for _ in 0..width*rows_count {
+        sand_graph.add_node();
+    }
+
+    for row in 0..rows_count {
+        for i in 0..width {
+            let this_node_idx = coords_to_index(i, row);
+            let x = i as f32 + row_offset(row);
+
+            for shift in down_shifts(row) {
+                if row + 1 == rows_count {
+                    sand_graph.add_edge(this_node_idx, SandGraph::SINK_NODE, 1);
+                    continue;
+                }
+                let j = (x + shift - row_offset(row + 1)).round() as i32;
+                let j = ((j % width as i32 + width as i32) % width as i32) as usize;
+                sand_graph.add_edge(this_node_idx, coords_to_index(j, row + 1), 1);
+            }
+
+            embedding.set_node_info(this_node_idx, [x, rows_y[row], 0.0], 0);
+        }
+    }
+
+    SandPileModel { graph: sand_graph, embedding }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+    use num_bigint::BigInt;
+    use num_traits::pow;
+    use model::SandPileModel;
+    use model::sand_graph::SandGraph;
+    use model::sandpile_group;
+    use controller::SandPileController;
+    use super::{Lattice, DirectedSquareLattice, DirectedHoneycombLattice};
+
+    fn assert_edges_go_down(model: &SandPileModel) {
+        for node_idx in model.graph.non_sink_nodes() {
+            let (coords, _) = model.embedding.get_node_info(node_idx);
+            for (_, neighbour_node_idx) in model.graph.successors(node_idx) {
+                if neighbour_node_idx != SandGraph::SINK_NODE {
+                    let (neighbour_coords, _) = model.embedding.get_node_info(neighbour_node_idx);
+                    assert!(neighbour_coords[1] < coords[1], "edge {} -> {} doesn't go down", node_idx, neighbour_node_idx);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn directed_edges_go_down() {
+        assert_edges_go_down(&DirectedSquareLattice::dhar_ramaswamy().get_lattice(&[6.0, 4.0, 0.0]));
+        assert_edges_go_down(&DirectedSquareLattice::with_diagonals().get_lattice(&[6.0, 4.0, 0.0]));
+        assert_edges_go_down(&DirectedHoneycombLattice::new().get_lattice(&[6.0, 4.0, 0.0]));
+    }
+
+    #[test]
+    fn honeycomb_out_degrees() {
+        let model = DirectedHoneycombLattice::new().get_lattice(&[6.0, 4.0, 0.0]);
+        let mut degrees_count = [0; 3];
+        for node_idx in model.graph.non_sink_nodes() {
+            let degree = model.graph.nodes[node_idx].degree;
+            assert!(degree == 1 || degree == 2, "node {} has out-degree {}", node_idx, degree);
+            degrees_count[degree as usize] += 1;
+        }
+        assert!(degrees_count[1] > 0 && degrees_count[2] > 0);
+    }
+
+    // Graph has no cycles, so every stable configuration is recurrent and group order is product of degrees, 2^N
+    #[test]
+    fn dhar_ramaswamy_group_order() {
+        let model = DirectedSquareLattice::dhar_ramaswamy().get_lattice(&[6.0, 3.0, 0.0]);
+        let nodes_count = model.graph.nodes.len() - 1;
+        assert!(model.graph.non_sink_nodes().all(|node_idx| model.graph.nodes[node_idx].degree == 2));
+        assert_eq!(sandpile_group::spanning_trees_count(&model.graph), pow(BigInt::from(2), nodes_count));
+    }
+
+    #[test]
+    fn dhar_ramaswamy_stable_configurations_are_recurrent() {
+        let model = DirectedSquareLattice::dhar_ramaswamy().get_lattice(&[3.0, 1.0, 0.0]);
+        let nodes_count = model.graph.nodes.len() - 1;
+        let mut controller = SandPileController::new(model);
+        for mask in 0..(1 << nodes_count) {
+            let configuration: Vec<i32> = (0..nodes_count).map(|i| (mask >> i) & 1).collect();
+            assert!(controller.is_recurrent(&configuration), "{:?} isn't recurrent", configuration);
+        }
+    }
+
+    // Uniform configuration is stationary, avalanches are started by a grain on a random node of the top row.
+    // P(size >= s) ~ s^(1 - 4/3), P(duration >= t) ~ t^(1 - 3/2), and every grain makes rows_count moves
+    // to sink while a toppling moves two grains, so mean size is rows_count / 2
+    #[test]
+    fn dhar_ramaswamy_avalanche_exponents() {
+        let width = 32;
+        let model = DirectedSquareLattice::dhar_ramaswamy().get_lattice(&[width as f32, 32.0, 0.0]);
+        let nodes_count = model.graph.nodes.len() - 1;
+        let rows_count = nodes_count / width;
+        let mut controller = SandPileController::new(model);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let configuration: Vec<i32> = (0..nodes_count).map(|_| rng.gen_range(0, 2)).collect();
+        controller.set_configuration(&configuration);
+
+        let avalanches_count = 20000;
+        let mut sizes = Vec::new();
+        let mut durations = Vec::new();
+        for _ in 0..avalanches_count {
+            let source = rng.gen_range(1, width + 1);
+            let toppled: Vec<usize> = controller.add_sand_with_waves(source, 1).into_iter().flatten().collect();
+            // row of node is (node_idx - 1) / width, one row topples per time step
+            let duration = toppled.iter().map(|node_idx| (node_idx - 1) / width + 1).max().unwrap_or(0);
+            sizes.push(toppled.len());
+            durations.push(duration);
+        }
+
+        let fraction_at_least = |values: &Vec<usize>, a: usize| {
+            values.iter().filter(|v| **v >= a).count() as f64 / values.len() as f64
+        };
+        let tau = 1.0 + (fraction_at_least(&sizes, 8) / fraction_at_least(&sizes, 128)).ln() / 16_f64.ln();
+        let duration_exponent = 1.0 + (fraction_at_least(&durations, 4) / fraction_at_least(&durations, 32)).ln() / 8_f64.ln();
+        let mean_size = sizes.iter().sum::<usize>() as f64 / avalanches_count as f64;
+
+        assert!((tau - 4.0 / 3.0).abs() < 0.05, "tau = {}", tau);
+        assert!((duration_exponent - 1.5).abs() < 0.05, "duration exponent = {}", duration_exponent);
+        assert!((mean_size / (rows_count as f64 / 2.0) - 1.0).abs() < 0.03, "mean size = {}, rows = {}", mean_size, rows_count);
+    }
+}
@@ -0,0 +1,138 @@
+use super::Lattice;
+
+use std::collections::BTreeMap;
+use model::SandPileModel;
+use model::region::Cuboid;
+use model::sand_graph::{SandGraph, NodeIndex};
+use model::embedding::{ EmbeddingToR3, Figure };
+
+
+pub struct SierpinskiGasket {
+    level: u32,
+}
+
+pub struct SierpinskiCarpet {
+    level: u32,
+}
+
+impl SierpinskiGasket {
+    pub fn new(level: u32) -> Self {
+        SierpinskiGasket { level }
+    }
+
+    // Lucas theorem: up-triangle (i, j) survives in level n gasket iff binomial(i + j, i) is odd
+    fn is_inside(i: usize, j: usize) -> bool {
+        i & j == 0
+    }
+}
+
+impl Lattice for SierpinskiGasket {
+    // Every smallest triangle is a node, triangles are connected through their common vertex.
+    // Each vertex of the gasket is shared by exactly two triangles, except three outer corners,
+    // whose triangles are connected to sink instead.
+    fn get_lattice(&self, cuboid_hull: &Cuboid) -> SandPileModel {
+        let [x_size, y_size, _] = *cuboid_hull;
+        let s3 = 3_f32.powf(0.5);
+        let triangles_on_side = 2_usize.pow(self.level);
+        let side = x_size.min(2.0 * y_size / s3) / (triangles_on_side as f32);
+
+        let mut sand_graph = SandGraph::new();
+        let mut embedding = EmbeddingToR3::new();
+
+        embedding.add_figure(Figure::polygon_on_circle(side / s3, 3, 90.0, 120.0));
+
+        // vertex (i, j) lies in i*e1 + j*e2, where e1 = (side, 0), e2 = (side/2, side*sqrt(3)/2)
+        let mut vertex_triangles: BTreeMap<(usize, usize), Vec<NodeIndex>> = BTreeMap::new();
+
+        for j in 0..triangles_on_side {
+            for i in 0..(triangles_on_side - j) {
+                if ! Self::is_inside(i, j) {
+                    continue;
+                }
+
+                let node_idx = sand_graph.add_node();
+                let x = side * (i as f32 + 0.5 * j as f32 + 0.5);
+                let y = side * s3 * (j as f32 / 2.0 + 1.0 / 6.0);
+                embedding.set_node_info(node_idx, [x, y, 0.0], 0);
+
+                for vertex in [(i, j), (i + 1, j), (i, j + 1)].iter() {
+                    vertex_triangles.entry(*vertex).or_insert_with(Vec::new).push(node_idx);
+                }
+            }
+        }
+
+        for (_, triangles) in &vertex_triangles {
+            match triangles[..] {
+                [node_idx] => sand_graph.add_edge(node_idx, SandGraph::SINK_NODE, 1),
+                [node_1_idx, node_2_idx] => {
+                    sand_graph.add_edge(node_1_idx, node_2_idx, 1);
+                    sand_graph.add_edge(node_2_idx, node_1_idx, 1);
+                },
+                _ => panic!("gasket vertex can't be shared by {} triangles", triangles.len()),
+            }
+        }
+
+        SandPileModel { graph: sand_graph, embedding }
+    }
+}
+
+
+impl SierpinskiCarpet {
+    pub fn new(level: u32) -> Self {
+        SierpinskiCarpet { level }
+    }
+
+    // cell (x, y) is removed iff on some level both base 3 digits are equal to 1
+    fn is_inside(x: usize, y: usize) -> bool {
+        let (mut x, mut y) = (x, y);
+        while x > 0 || y > 0 {
+            if x % 3 == 1 && y % 3 == 1 {
+                return false;
+            }
+            x /= 3;
+            y /= 3;
+        }
+        true
+    }
+}
+
+impl Lattice for SierpinskiCarpet {
+    // Every remaining square is a node with 4-neighbourhood.
+    // Squares on outer edge are connected to sink, borders of holes are reflecting.
+    fn get_lattice(&self, cuboid_hull: &Cuboid) -> SandPileModel {
+        let [x_size, y_size, _] = *cuboid_hull;
+        let cells_on_side = 3_usize.pow(self.level);
+        let side = x_size.min(y_size) / (cells_on_side as f32);
+
+        let mut sand_graph = SandGraph::new();
+        let mut embedding = EmbeddingToR3::new();
+
+        embedding.add_figure(Figure::polygon_on_circle(side / 2_f32.powf(0.5), 4, 45.0, 90.0));
+
+        let mut cell_to_node: BTreeMap<(usize, usize), NodeIndex> = BTreeMap::new();
+        for y in 0..cells_on_side {
+            for x in 0..cells_on_side {
+                if Self::is_inside(x, y) {
+                    let node_idx = sand_graph.add_node();
+                    let coords = [side * (x as f32 + 0.5), side * (y as f32 + 0.5), 0.0];
+                    embedding.set_node_info(node_idx, coords, 0);
+                    cell_to_node.insert((x, y), node_idx);
+                }
+            }
+        }
+
+        let neighbours = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+        for ((x, y), node_idx) in &cell_to_node {
+            for (dx, dy) in neighbours.iter() {
+                let (nx, ny) = (*x as i64 + dx, *y as i64 + dy);
+                if nx < 0 || ny < 0 || nx >= cells_on_side as i64 || ny >= cells_on_side as i64 {
+                    sand_graph.add_edge(*node_idx, SandGraph::SINK_NODE, 1);
+                } else if let Some(neighbour_idx) = cell_to_node.get(&(nx as usize, ny as usize)) {
+                    sand_graph.add_edge(*node_idx, *neighbour_idx, 1);
+                }
+            }
+        }
+
+        SandPileModel { graph: sand_graph, embedding }
+    }
+}
@@ -1,11 +1,23 @@
 mod regular_lattice;
 mod uniform_lattice;
+mod fractal_lattice;
+mod tree_lattice;
+mod recipe;
+mod random_lattice;
+mod directed_lattice;
 mod tilings;
 mod utils;
 
 
-pub use self::regular_lattice::{SquareLattice, TriangleLattice, HexagonLattice, CubeLattice};
+pub use self::regular_lattice::{SquareLattice, TriangleLattice, HexagonLattice, CubeLattice,
+                                  HypercubicLattice, TorusLattice};
 pub use self::uniform_lattice::{SemiRegularLattice, KUniformLattice, TetrahedralOctahedral};
+pub use self::fractal_lattice::{SierpinskiGasket, SierpinskiCarpet};
+pub use self::tree_lattice::TreeLattice;
+pub use self::recipe::{TilingRecipe, RecipeLattice};
+pub use self::utils::{TilingGraph, TilingOptions, Neighbourhood, EdgeWeighting};
+pub use self::random_lattice::{RandomLattice, RandomGraphKind, SinkRule};
+pub use self::directed_lattice::{DirectedSquareLattice, DirectedHoneycombLattice};
 
 use graphics::math;
 use model::SandPileModel;
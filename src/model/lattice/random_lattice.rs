@@ -0,0 +1,259 @@
+use super::Lattice;
+
+use std::collections::BTreeSet;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use model::SandPileModel;
+use model::layout;
+use model::region::Cuboid;
+use model::sand_graph::SandGraph;
+use model::embedding::{ EmbeddingToR3, Figure };
+
+
+#[derive(Clone, Copy, Debug)]
+pub enum RandomGraphKind {
+    // every pair of nodes is connected with probability p
+    ErdosRenyi { nodes_count: usize, p: f64 },
+    // every node has exactly degree neighbours
+    RandomRegular { nodes_count: usize, degree: usize },
+    // ring where every node is connected to neighbours_count nearest nodes, each edge is rewired with probability beta
+    WattsStrogatz { nodes_count: usize, neighbours_count: usize, beta: f64 },
+    // preferential attachment, every new node is connected to edges_per_node existing ones
+    BarabasiAlbert { nodes_count: usize, edges_per_node: usize },
+}
+
+// How random graph is connected to sink.
+// After the rule is applied every connected component without sink edges
+// gets one edge to sink from its first node, so every configuration stabilizes.
+#[derive(Clone, Copy, Debug)]
+pub enum SinkRule {
+    // every node leaks one grain to sink with probability p
+    Leak(f64),
+    // nodes of smaller degree are topped up to this degree by edges to sink
+    TopUp(usize),
+}
+
+pub struct RandomLattice {
+    kind: RandomGraphKind,
+    sink_rule: SinkRule,
+    seed: u64,
+}
+
+impl RandomLattice {
+    pub const REGULAR_GRAPH_ATTEMPTS: usize = 1000;
+
+    pub fn new(kind: RandomGraphKind, seed: u64) -> Self {
+        RandomLattice { kind, sink_rule: SinkRule::Leak(0.1), seed }
+    }
+
+    pub fn erdos_renyi(nodes_count: usize, p: f64, seed: u64) -> Self {
+        assert!(0.0 <= p && p <= 1.0, "edge probability have to be in [0, 1]");
+        Self::new(RandomGraphKind::ErdosRenyi { nodes_count, p }, seed)
+    }
+
+    pub fn random_regular(nodes_count: usize, degree: usize, seed: u64) -> Self {
+        assert!(degree < nodes_count, "regular graph degree have to be less than nodes count");
+        assert_eq!((nodes_count * degree) % 2, 0, "nodes count times degree have to be even");
+        Self::new(RandomGraphKind::RandomRegular { nodes_count, degree }, seed)
+    }
+
+    pub fn watts_strogatz(nodes_count: usize, neighbours_count: usize, beta: f64, seed: u64) -> Self {
+        assert_eq!(neighbours_count % 2, 0, "Watts-Strogatz neighbours count have to be even");
+        assert!(neighbours_count < nodes_count, "Watts-Strogatz neighbours count have to be less than nodes count");
+        assert!(0.0 <= beta && beta <= 1.0, "rewiring probability have to be in [0, 1]");
+        Self::new(RandomGraphKind::WattsStrogatz { nodes_count, neighbours_count, beta }, seed)
+    }
+
+    pub fn barabasi_albert(nodes_count: usize, edges_per_node: usize, seed: u64) -> Self {
+        assert!(edges_per_node > 0, "Barabasi-Albert node have to add at least one edge");
+        assert!(edges_per_node < nodes_count, "Barabasi-Albert edges per node have to be less than nodes count");
+        Self::new(RandomGraphKind::BarabasiAlbert { nodes_count, edges_per_node }, seed)
+    }
+
+    pub fn with_sink_rule(mut self, sink_rule: SinkRule) -> Self {
+        self.sink_rule = sink_rule;
+        self
+    }
+
+    fn erdos_renyi_graph(nodes_count: usize, p: f64, rng: &mut StdRng) -> Vec<BTreeSet<usize>> {
+        let mut adjacency = vec![BTreeSet::new(); nodes_count];
+        for i in 0..nodes_count {
+            for j in (i + 1)..nodes_count {
+                if rng.gen_bool(p) {
+                    adjacency[i].insert(j);
+                    adjacency[j].insert(i);
+                }
+            }
+        }
+        adjacency
+    }
+
+    // pairing model: join random pair of free stubs while it doesn't make loop or multiple edge,
+    // start from scratch when stuck
+    fn random_regular_graph(nodes_count: usize, degree: usize, rng: &mut StdRng) -> Vec<BTreeSet<usize>> {
+        for _ in 0..Self::REGULAR_GRAPH_ATTEMPTS {
+            let mut adjacency = vec![BTreeSet::new(); nodes_count];
+            let mut stubs: Vec<usize> = (0..nodes_count).flat_map(|i| vec![i; degree]).collect();
+            let mut is_stuck = false;
+
+            while ! stubs.is_empty() && ! is_stuck {
+                is_stuck = true;
+                for _ in 0..stubs.len() * stubs.len() {
+                    let a = rng.gen_range(0, stubs.len());
+                    let b = rng.gen_range(0, stubs.len());
+                    let (u, v) = (stubs[a], stubs[b]);
+                    if u != v && ! adjacency[u].contains(&v) {
+                        adjacency[u].insert(v);
+                        adjacency[v].insert(u);
+                        stubs.swap_remove(a.max(b));
+                        stubs.swap_remove(a.min(b));
+                        is_stuck = false;
+                        break;
+                    }
+                }
+            }
+
+            if ! is_stuck {
+                return adjacency;
+            }
+        }
+        panic!("Failed to generate random {}-regular graph on {} nodes", degree, nodes_count);
+    }
+
+    fn watts_strogatz_graph(nodes_count: usize, neighbours_count: usize, beta: f64, rng: &mut StdRng) -> Vec<BTreeSet<usize>> {
+        let mut adjacency = vec![BTreeSet::new(); nodes_count];
+        for i in 0..nodes_count {
+            for j in 1..=neighbours_count / 2 {
+                let k = (i + j) % nodes_count;
+                adjacency[i].insert(k);
+                adjacency[k].insert(i);
+            }
+        }
+
+        for j in 1..=neighbours_count / 2 {
+            for i in 0..nodes_count {
+                let k = (i + j) % nodes_count;
+                if ! adjacency[i].contains(&k) || ! rng.gen_bool(beta) || adjacency[i].len() == nodes_count - 1 {
+                    continue;
+                }
+                let mut new_k = rng.gen_range(0, nodes_count);
+                while new_k == i || adjacency[i].contains(&new_k) {
+                    new_k = rng.gen_range(0, nodes_count);
+                }
+                adjacency[i].remove(&k);
+                adjacency[k].remove(&i);
+                adjacency[i].insert(new_k);
+                adjacency[new_k].insert(i);
+            }
+        }
+        adjacency
+    }
+
+    // starts from complete graph on edges_per_node + 1 nodes
+    fn barabasi_albert_graph(nodes_count: usize, edges_per_node: usize, rng: &mut StdRng) -> Vec<BTreeSet<usize>> {
+        let mut adjacency = vec![BTreeSet::new(); nodes_count];
+        // every node appears here as many times as its degree
+        let mut edge_ends: Vec<usize> = Vec::new();
+
+        for i in 0..=edges_per_node {
+            for j in (i + 1)..=edges_per_node {
+                adjacency[i].insert(j);
+                adjacency[j].insert(i);
+                edge_ends.push(i);
+                edge_ends.push(j);
+            }
+        }
+
+        for i in (edges_per_node + 1)..nodes_count {
+            let mut targets: BTreeSet<usize> = BTreeSet::new();
+            while targets.len() < edges_per_node {
+                targets.insert(*edge_ends.choose(rng).unwrap());
+            }
+            for target in targets {
+                adjacency[i].insert(target);
+                adjacency[target].insert(i);
+                edge_ends.push(i);
+                edge_ends.push(target);
+            }
+        }
+        adjacency
+    }
+
+    fn connect_to_sink(&self, sand_graph: &mut SandGraph, adjacency: &Vec<BTreeSet<usize>>, rng: &mut StdRng) {
+        let mut has_sink_edge = vec![false; adjacency.len()];
+        for (i, neighbours) in adjacency.iter().enumerate() {
+            let sink_weight = match self.sink_rule {
+                SinkRule::Leak(p) => if rng.gen_bool(p) { 1 } else { 0 },
+                SinkRule::TopUp(degree) => degree.saturating_sub(neighbours.len()),
+            };
+            if sink_weight > 0 {
+                sand_graph.add_edge(i + 1, SandGraph::SINK_NODE, sink_weight as i32);
+                has_sink_edge[i] = true;
+            }
+        }
+
+        // make every component dissipative
+        let mut is_visited = vec![false; adjacency.len()];
+        for start in 0..adjacency.len() {
+            if is_visited[start] {
+                continue;
+            }
+            is_visited[start] = true;
+            let mut stack = vec![start];
+            let mut component_has_sink_edge = false;
+            while let Some(v) = stack.pop() {
+                component_has_sink_edge |= has_sink_edge[v];
+                for u in &adjacency[v] {
+                    if ! is_visited[*u] {
+                        is_visited[*u] = true;
+                        stack.push(*u);
+                    }
+                }
+            }
+            if ! component_has_sink_edge {
+                sand_graph.add_edge(start + 1, SandGraph::SINK_NODE, 1);
+            }
+        }
+    }
+}
+
+impl Lattice for RandomLattice {
+    fn get_lattice(&self, cuboid_hull: &Cuboid) -> SandPileModel {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+
+        let adjacency = match self.kind {
+            RandomGraphKind::ErdosRenyi { nodes_count, p } =>
+                Self::erdos_renyi_graph(nodes_count, p, &mut rng),
+            RandomGraphKind::RandomRegular { nodes_count, degree } =>
+                Self::random_regular_graph(nodes_count, degree, &mut rng),
+            RandomGraphKind::WattsStrogatz { nodes_count, neighbours_count, beta } =>
+                Self::watts_strogatz_graph(nodes_count, neighbours_count, beta, &mut rng),
+            RandomGraphKind::BarabasiAlbert { nodes_count, edges_per_node } =>
+                Self::barabasi_albert_graph(nodes_count, edges_per_node, &mut rng),
+        };
+
+        let mut sand_graph = SandGraph::new();
+        for _ in 0..adjacency.len() {
+            sand_graph.add_node();
+        }
+        for (i, neighbours) in adjacency.iter().enumerate() {
+            for j in neighbours {
+                sand_graph.add_edge(i + 1, j + 1, 1);
+            }
+        }
+        self.connect_to_sink(&mut sand_graph, &adjacency, &mut rng);
+
+        let mut embedding = EmbeddingToR3::new();
+        let [x_size, y_size, _] = *cuboid_hull;
+        let spacing = (x_size * y_size / adjacency.len().max(1) as f32).sqrt();
+        let figure_idx = embedding.add_figure(Figure::polygon_on_circle(0.2 * spacing, 8, 0.0, 45.0));
+
+        let coords = layout::force_directed(&sand_graph, cuboid_hull, &mut rng);
+        for node_idx in sand_graph.non_sink_nodes() {
+            embedding.set_node_info(node_idx, coords[node_idx], figure_idx);
+        }
+
+        SandPileModel { graph: sand_graph, embedding }
+    }
+}
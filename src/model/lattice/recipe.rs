@@ -0,0 +1,174 @@
+use super::Lattice;
+use super::utils::{Constructor, TilingGraph, TilingOptions, continue_tiling_by_translation};
+
+use std::fs;
+use std::path::Path;
+use model::SandPileModel;
+use model::region::Cuboid;
+
+// Text description of k-uniform tiling, same steps as hand-written functions in tilings.rs.
+// One command per line, everything after '#' is a comment:
+//
+//     seed <sides_count>                        first figure, placed in the center of the hull
+//     rotate <degrees>                          rotation of the seed figure, optional, default 0
+//     add <figure_idx> <side_idx> <sides_count> Constructor::add
+//     vector <figure_idx_1> <figure_idx_2>      translation vector, exactly two of them
+//
+// Example, [3^6; 3^3.4^2]_1:
+//
+//     seed 4
+//     add 0 1 3
+//     add 0 3 3
+//     add 2 2 3
+//     add 1 2 3
+//     add 1 1 3
+//     add 5 2 3
+//     vector 3 6
+//     vector 4 5
+#[derive(Clone, Debug)]
+pub struct TilingRecipe {
+    pub seed_sides_count: usize,
+    pub rotate_in_degrees: usize,
+    pub steps: Vec<(usize, usize, usize)>,
+    pub vectors: Vec<(usize, usize)>,
+}
+
+pub struct RecipeLattice {
+    recipe: TilingRecipe,
+    options: TilingOptions,
+}
+
+impl TilingRecipe {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut seed_sides_count: Option<usize> = None;
+        let mut rotate_in_degrees = 0;
+        let mut steps: Vec<(usize, usize, usize)> = Vec::new();
+        let mut vectors: Vec<(usize, usize)> = Vec::new();
+
+        // sides count of every figure added so far
+        let mut figures: Vec<usize> = Vec::new();
+
+        for (line_idx, line) in text.lines().enumerate() {
+            let line_num = line_idx + 1;
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            let command = words.next().unwrap();
+            let args: Result<Vec<usize>, _> = words.map(|w| w.parse::<usize>()).collect();
+            let args = args.map_err(|e| format!("line {}: {}", line_num, e))?;
+
+            let expected_args_count = match command {
+                "seed" | "rotate" => 1,
+                "add" => 3,
+                "vector" => 2,
+                _ => return Err(format!("line {}: unknown command '{}'", line_num, command)),
+            };
+            if args.len() != expected_args_count {
+                return Err(format!("line {}: '{}' expects {} arguments, got {}",
+                                   line_num, command, expected_args_count, args.len()));
+            }
+            if command != "seed" && command != "rotate" && seed_sides_count.is_none() {
+                return Err(format!("line {}: '{}' before 'seed'", line_num, command));
+            }
+
+            match command {
+                "seed" => {
+                    if seed_sides_count.is_some() {
+                        return Err(format!("line {}: seed figure is already set", line_num));
+                    }
+                    Self::check_sides_count(args[0], line_num)?;
+                    seed_sides_count = Some(args[0]);
+                    figures.push(args[0]);
+                },
+                "rotate" => rotate_in_degrees = args[0],
+                "add" => {
+                    let (figure_idx, side_idx, sides_count) = (args[0], args[1], args[2]);
+                    Self::check_figure_idx(figure_idx, figures.len(), line_num)?;
+                    if side_idx >= figures[figure_idx] {
+                        return Err(format!("line {}: figure {} has only {} sides, got side {}",
+                                           line_num, figure_idx, figures[figure_idx], side_idx));
+                    }
+                    Self::check_sides_count(sides_count, line_num)?;
+                    steps.push((figure_idx, side_idx, sides_count));
+                    figures.push(sides_count);
+                },
+                _ => {
+                    Self::check_figure_idx(args[0], figures.len(), line_num)?;
+                    Self::check_figure_idx(args[1], figures.len(), line_num)?;
+                    vectors.push((args[0], args[1]));
+                },
+            }
+        }
+
+        let seed_sides_count = seed_sides_count.ok_or("recipe has no 'seed' command".to_string())?;
+        if vectors.len() != 2 {
+            return Err(format!("recipe has to contain exactly 2 translation vectors, got {}", vectors.len()));
+        }
+
+        Ok(TilingRecipe { seed_sides_count, rotate_in_degrees, steps, vectors })
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let text = fs::read_to_string(&path)
+            .map_err(|e| format!("can't read {}: {}", path.as_ref().display(), e))?;
+        Self::parse(&text)
+    }
+
+    fn check_sides_count(sides_count: usize, line_num: usize) -> Result<(), String> {
+        if sides_count < 3 {
+            return Err(format!("line {}: figure have to have at least 3 sides, got {}", line_num, sides_count));
+        }
+        Ok(())
+    }
+
+    fn check_figure_idx(figure_idx: usize, figures_count: usize, line_num: usize) -> Result<(), String> {
+        if figure_idx >= figures_count {
+            return Err(format!("line {}: figure {} doesn't exist yet, there are only {} figures",
+                               line_num, figure_idx, figures_count));
+        }
+        Ok(())
+    }
+}
+
+impl RecipeLattice {
+    pub fn new(recipe: TilingRecipe) -> Self {
+        RecipeLattice { recipe, options: TilingOptions::new() }
+    }
+
+    pub fn with_graph(mut self, graph: TilingGraph) -> Self {
+        self.options.graph = graph;
+        self
+    }
+
+    pub fn with_options(mut self, options: TilingOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        TilingRecipe::from_file(path).map(Self::new)
+    }
+}
+
+impl Lattice for RecipeLattice {
+    fn get_lattice(&self, cuboid_hull: &Cuboid) -> SandPileModel {
+        let recipe = &self.recipe;
+        let origin = vecmath::vec3_scale(*cuboid_hull, 0.5);
+        let mut constructor =
+            Constructor::new(origin, recipe.rotate_in_degrees, 1.0, recipe.seed_sides_count);
+
+        for (figure_idx, side_idx, sides_count) in &recipe.steps {
+            constructor.add(*figure_idx, *side_idx, *sides_count);
+        }
+
+        let v1 = constructor.get_vector(recipe.vectors[0].0, recipe.vectors[0].1);
+        let v2 = constructor.get_vector(recipe.vectors[1].0, recipe.vectors[1].1);
+
+        continue_tiling_by_translation(&mut constructor.tiling, (v1, v2), cuboid_hull);
+        constructor.tiling.options = self.options;
+        constructor.tiling.build()
+    }
+}
@@ -6,46 +6,90 @@ use model::region::Cuboid;
 use model::sand_graph::{SandGraph, NodeIndex};
 use model::embedding::{ EmbeddingToR3, Figure };
 use super::tilings::{ tiling_square, tiling_hexagon, tiling_triangle };
+use super::utils::{TilingGraph, TilingOptions};
 
 
-pub struct SquareLattice {}
-pub struct HexagonLattice {}
-pub struct TriangleLattice {}
+pub struct SquareLattice {
+    options: TilingOptions,
+}
+pub struct HexagonLattice {
+    options: TilingOptions,
+}
+pub struct TriangleLattice {
+    options: TilingOptions,
+}
 pub struct CubeLattice {}
+// Square lattice on the torus, sides are glued together, so there is no edge to sink
+pub struct TorusLattice {}
+pub struct HypercubicLattice {
+    dimension: usize,
+    extra_side: usize,
+    projection: Vec<math::Vec3d<f32>>,
+}
 
 impl SquareLattice {
     pub fn new() -> SquareLattice {
-        SquareLattice {}
+        SquareLattice { options: TilingOptions::new() }
+    }
+
+    pub fn with_graph(mut self, graph: TilingGraph) -> Self {
+        self.options.graph = graph;
+        self
+    }
+
+    pub fn with_options(mut self, options: TilingOptions) -> Self {
+        self.options = options;
+        self
     }
 }
 
 impl Lattice for SquareLattice {
     fn get_lattice(&self, cuboid_hull: &Cuboid) -> SandPileModel {
-        tiling_square(cuboid_hull, 0)
+        tiling_square(cuboid_hull, 0).with_options(self.options).build()
     }
 }
 
 impl HexagonLattice {
     pub fn new() -> HexagonLattice {
-        HexagonLattice {}
+        HexagonLattice { options: TilingOptions::new() }
+    }
+
+    pub fn with_graph(mut self, graph: TilingGraph) -> Self {
+        self.options.graph = graph;
+        self
+    }
+
+    pub fn with_options(mut self, options: TilingOptions) -> Self {
+        self.options = options;
+        self
     }
 }
 
 impl Lattice for HexagonLattice {
     fn get_lattice(&self, cuboid_hull: &Cuboid) -> SandPileModel {
-        tiling_hexagon(cuboid_hull, 0)
+        tiling_hexagon(cuboid_hull, 0).with_options(self.options).build()
     }
 }
 
 impl TriangleLattice {
     pub fn new() -> TriangleLattice {
-        TriangleLattice {}
+        TriangleLattice { options: TilingOptions::new() }
+    }
+
+    pub fn with_graph(mut self, graph: TilingGraph) -> Self {
+        self.options.graph = graph;
+        self
+    }
+
+    pub fn with_options(mut self, options: TilingOptions) -> Self {
+        self.options = options;
+        self
     }
 }
 
 impl Lattice for TriangleLattice {
     fn get_lattice(&self, cuboid_hull: &Cuboid) -> SandPileModel {
-        tiling_triangle(cuboid_hull, 0)
+        tiling_triangle(cuboid_hull, 0).with_options(self.options).build()
     }
 }
 
@@ -54,8 +98,8 @@ impl CubeLattice {
         CubeLattice {}
     }
 
-    fn cube() -> Figure {
-        let s = 0.5_f32;  // half of side length
+    fn cube(side: f32) -> Figure {
+        let s = side / 2.0;  // half of side length
         let vertices = vec![
             [-s, -s, s], [-s, s, s], [s, s, s], [s, -s, s],         // up square
             [-s, -s, -s], [-s, s, -s], [s, s, -s], [s, -s, -s],     // down square
@@ -91,7 +135,7 @@ impl Lattice for CubeLattice {
         let mut sand_graph = SandGraph::new();
         let mut embedding = EmbeddingToR3::new();
 
-        let figure = Self::cube();
+        let figure = Self::cube(1.0);
         embedding.add_figure(figure);
 
 
@@ -130,3 +174,151 @@ impl Lattice for CubeLattice {
         SandPileModel {graph: sand_graph, embedding }
     }
 }
+
+
+impl TorusLattice {
+    pub fn new() -> Self {
+        TorusLattice {}
+    }
+}
+
+impl Lattice for TorusLattice {
+    fn get_lattice(&self, cuboid_hull: &Cuboid) -> SandPileModel {
+        let x_size = cuboid_hull[0] as usize;
+        let y_size = cuboid_hull[1] as usize;
+
+        let coords_to_index = |x: usize, y: usize| { y*x_size + x + 1 };
+
+        let mut sand_graph = SandGraph::new();
+        let mut embedding = EmbeddingToR3::new();
+        embedding.add_figure(Figure::polygon_on_circle(0.5_f32.powf(0.5), 4, 45.0, 90.0));
+
+        for _ in 0..x_size*y_size {
+            sand_graph.add_node();
+        }
+        for x in 0..x_size {
+            for y in 0..y_size {
+                let this_node_idx = coords_to_index(x, y);
+                sand_graph.add_edge(this_node_idx, coords_to_index((x + 1) % x_size, y), 1);
+                sand_graph.add_edge(this_node_idx, coords_to_index((x + x_size - 1) % x_size, y), 1);
+                sand_graph.add_edge(this_node_idx, coords_to_index(x, (y + 1) % y_size), 1);
+                sand_graph.add_edge(this_node_idx, coords_to_index(x, (y + y_size - 1) % y_size), 1);
+
+                embedding.set_node_info(this_node_idx, [x as f32 + 0.5, y as f32 + 0.5, 0.0], 0);
+            }
+        }
+
+        SandPileModel {graph: sand_graph, embedding }
+    }
+}
+
+
+impl HypercubicLattice {
+    // Z^d with axes 0, 1, 2 sized by the cuboid hull and all other axes of length extra_side.
+    // By default axis k >= 3 is projected onto axis (k - 3) % 3 with step extra_side^-(1 + (k - 3) / 3),
+    // so every extra coordinate is nested inside the unit cell of the 3D site and no two nodes collide.
+    pub fn new(dimension: usize, extra_side: usize) -> Self {
+        assert!(dimension > 0, "lattice dimension have to be positive");
+        assert!(extra_side > 0, "extra dimensions have to contain at least one node");
+
+        let mut projection: Vec<math::Vec3d<f32>> = Vec::new();
+        for axis in 0..dimension {
+            let mut v = [0.0; 3];
+            if axis < 3 {
+                v[axis] = 1.0;
+            } else {
+                let level = ((axis - 3) / 3 + 1) as i32;
+                v[(axis - 3) % 3] = (extra_side as f32).powi(-level);
+            }
+            projection.push(v);
+        }
+
+        HypercubicLattice { dimension, extra_side, projection }
+    }
+
+    // Replace default projection by an arbitrary linear map R^d -> R^3, given as images of the basis vectors.
+    pub fn with_projection(mut self, projection: Vec<math::Vec3d<f32>>) -> Self {
+        assert_eq!(projection.len(), self.dimension,
+                   "projection have to contain one vector per lattice axis");
+        self.projection = projection;
+        self
+    }
+
+    fn sizes(&self, cuboid_hull: &Cuboid) -> Vec<usize> {
+        (0..self.dimension)
+            .map(|axis| if axis < 3 { cuboid_hull[axis] as usize } else { self.extra_side })
+            .collect()
+    }
+
+    fn project(&self, coords: &Vec<usize>) -> math::Vec3d<f32> {
+        let mut result = [0.0; 3];
+        for (axis, c) in coords.iter().enumerate() {
+            result = vecmath::vec3_add(result, vecmath::vec3_scale(self.projection[axis], *c as f32));
+        }
+        result
+    }
+}
+
+impl Lattice for HypercubicLattice {
+    fn get_lattice(&self, cuboid_hull: &Cuboid) -> SandPileModel {
+        let sizes = self.sizes(cuboid_hull);
+        let nodes_count: usize = sizes.iter().product();
+
+        // mixed radix numbering, axis 0 is the least significant digit
+        let coords_to_index = |coords: &Vec<usize>| {
+            let mut idx = 0;
+            for axis in (0..coords.len()).rev() {
+                idx = idx*sizes[axis] + coords[axis];
+            }
+            idx + 1
+        };
+        let index_to_coords = |idx: NodeIndex| {
+            let mut rest = idx - 1;
+            let mut coords = Vec::with_capacity(sizes.len());
+            for size in &sizes {
+                coords.push(rest % size);
+                rest /= size;
+            }
+            coords
+        };
+
+        let mut sand_graph = SandGraph::new();
+        let mut embedding = EmbeddingToR3::new();
+
+        let min_step = self.projection
+            .iter()
+            .map(|v| vecmath::vec3_len(*v))
+            .filter(|l| *l > 0.0)
+            .fold(1.0_f32, f32::min);
+        embedding.add_figure(CubeLattice::cube(min_step));
+
+        for _ in 0..nodes_count {
+            sand_graph.add_node();
+        }
+
+        for node_idx in sand_graph.non_sink_nodes() {
+            let coords = index_to_coords(node_idx);
+
+            for axis in 0..self.dimension {
+                let mut neighbour = coords.clone();
+                if coords[axis] + 1 < sizes[axis] {
+                    neighbour[axis] = coords[axis] + 1;
+                    sand_graph.add_edge(node_idx, coords_to_index(&neighbour), 1);
+                } else {
+                    sand_graph.add_edge(node_idx, SandGraph::SINK_NODE, 1);
+                }
+
+                if coords[axis] > 0 {
+                    neighbour[axis] = coords[axis] - 1;
+                    sand_graph.add_edge(node_idx, coords_to_index(&neighbour), 1);
+                } else {
+                    sand_graph.add_edge(node_idx, SandGraph::SINK_NODE, 1);
+                }
+            }
+
+            embedding.set_node_info(node_idx, self.project(&coords), 0);
+        }
+
+        SandPileModel {graph: sand_graph, embedding }
+    }
+}
@@ -1,9 +1,8 @@
-use model::SandPileModel;
 use model::region::Cuboid;
-use super::utils::{Constructor, continue_tiling_by_translation};
+use super::utils::{Constructor, UniformTiling, continue_tiling_by_translation};
 
 
-pub(super) fn tiling_square(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandPileModel {
+pub(super) fn tiling_square(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> UniformTiling {
     // 1-uniform
     // [4^4]
     // p4m
@@ -17,10 +16,10 @@ pub(super) fn tiling_square(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> S
     let v2 = constructor.get_vector(0, 2);
 
     continue_tiling_by_translation(&mut constructor.tiling, (v1, v2), cuboid_hull);
-    constructor.tiling.build()
+    constructor.tiling
 }
 
-pub(super) fn tiling_hexagon(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandPileModel {
+pub(super) fn tiling_hexagon(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> UniformTiling {
     // 1-uniform
     // [6^3]
     // p4m
@@ -34,10 +33,10 @@ pub(super) fn tiling_hexagon(cuboid_hull: &Cuboid, rotate_in_degrees: usize) ->
     let v2 = constructor.get_vector(0, 2);
 
     continue_tiling_by_translation(&mut constructor.tiling, (v1, v2), cuboid_hull);
-    constructor.tiling.build()
+    constructor.tiling
 }
 
-pub(super) fn tiling_triangle(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandPileModel {
+pub(super) fn tiling_triangle(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> UniformTiling {
     // 1-uniform
     // [3^6]
     // p4m
@@ -51,11 +50,11 @@ pub(super) fn tiling_triangle(cuboid_hull: &Cuboid, rotate_in_degrees: usize) ->
     let v2 = constructor.get_vector(0, 2);
 
     continue_tiling_by_translation(&mut constructor.tiling, (v1, v2), cuboid_hull);
-    constructor.tiling.build()
+    constructor.tiling
 }
 
 
-pub(super) fn tiling_1(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandPileModel {
+pub(super) fn tiling_1(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> UniformTiling {
     // 1-uniform
     // 3.3.3.3.6
     // p6, 632
@@ -75,11 +74,11 @@ pub(super) fn tiling_1(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandPi
     let v2 = constructor.get_vector(3, 17);
 
     continue_tiling_by_translation(&mut constructor.tiling, (v1, v2), cuboid_hull);
-    constructor.tiling.build()
+    constructor.tiling
 }
 
 
-pub(super) fn tiling_2(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandPileModel {
+pub(super) fn tiling_2(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> UniformTiling {
     // 2-uniform
     // [3^6; 3^2.4.3.4]
     // p6m, *632
@@ -102,11 +101,11 @@ pub(super) fn tiling_2(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandPi
     let v2 = constructor.get_vector(6, 9);
 
     continue_tiling_by_translation(&mut constructor.tiling, (v1, v2), cuboid_hull);
-    constructor.tiling.build()
+    constructor.tiling
 }
 
 
-pub(super) fn tiling_3(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandPileModel {
+pub(super) fn tiling_3(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> UniformTiling {
     // 2-uniform
     // [3^6; 3^3.4^2]_1
     // pmm, *2222
@@ -125,10 +124,10 @@ pub(super) fn tiling_3(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandPi
     let v2 = constructor.get_vector(4, 5);
 
     continue_tiling_by_translation(&mut constructor.tiling, (v1, v2), cuboid_hull);
-    constructor.tiling.build()
+    constructor.tiling
 }
 
-pub(super) fn tiling_4(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandPileModel {
+pub(super) fn tiling_4(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> UniformTiling {
     // 2-uniform
     // [4^4; 3^3.4^2]_1
     // cmm, 2*22
@@ -145,10 +144,10 @@ pub(super) fn tiling_4(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandPi
     let v2 = constructor.get_vector(0, 5);
 
     continue_tiling_by_translation(&mut constructor.tiling, (v1, v2), cuboid_hull);
-    constructor.tiling.build()
+    constructor.tiling
 }
 
-pub(super) fn tiling_5(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandPileModel {
+pub(super) fn tiling_5(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> UniformTiling {
     // uniform
     // [3^3.4^2; 3^2.4.3.4]_2
     // pgg, 22×
@@ -177,10 +176,10 @@ pub(super) fn tiling_5(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandPi
     let v2 = constructor.get_vector(3, 14);
 
     continue_tiling_by_translation(&mut constructor.tiling, (v1, v2), cuboid_hull);
-    constructor.tiling.build()
+    constructor.tiling
 }
 
-pub(super) fn tiling_6(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandPileModel {
+pub(super) fn tiling_6(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> UniformTiling {
     // 4-uniform
     // [3.3.4.12; 3.4.3.12; 3.4.6.4; 4.6.12]
     // cmm
@@ -202,10 +201,10 @@ pub(super) fn tiling_6(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandPi
     let v2 = constructor.get_vector(11, 13);
 
     continue_tiling_by_translation(&mut constructor.tiling, (v1, v2), cuboid_hull);
-    constructor.tiling.build()
+    constructor.tiling
 }
 
-pub(super) fn tiling_7(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandPileModel {
+pub(super) fn tiling_7(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> UniformTiling {
     // 3-uniform
     // [3^3.4^2; 3^2.4.3.4; 4^4]
     // p4
@@ -227,10 +226,10 @@ pub(super) fn tiling_7(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandPi
     let v2 = constructor.get_vector(5, 14);
 
     continue_tiling_by_translation(&mut constructor.tiling, (v1, v2), cuboid_hull);
-    constructor.tiling.build()
+    constructor.tiling
 }
 
-pub(super) fn tiling_8(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandPileModel {
+pub(super) fn tiling_8(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> UniformTiling {
     // 6-uniform
     // [3^6; 3^4.6; 3^3.4^2; 3^2.4.3.4; 3^2.6^2; 3.4^2.6]
     // p31m
@@ -276,10 +275,10 @@ pub(super) fn tiling_8(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandPi
     let v2 = constructor.get_vector(2, 59);
 
     continue_tiling_by_translation(&mut constructor.tiling, (v1, v2), cuboid_hull);
-    constructor.tiling.build()
+    constructor.tiling
 }
 
-pub(super) fn tiling_9(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandPileModel {
+pub(super) fn tiling_9(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> UniformTiling {
     // 3-uniform
     // [3^6; 3^2.4.12; 4.6.12]
     // p3m1
@@ -303,11 +302,11 @@ pub(super) fn tiling_9(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandPi
     let v2 = constructor.get_vector(1, 6);
 
     continue_tiling_by_translation(&mut constructor.tiling, (v1, v2), cuboid_hull);
-    constructor.tiling.build()
+    constructor.tiling
 }
 
 
-pub(super) fn tiling_10(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandPileModel {
+pub(super) fn tiling_10(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> UniformTiling {
     // 3-uniform
     // [3^6; 3^2.4.3.4; 3.4^2.6]
     // p6m
@@ -330,10 +329,10 @@ pub(super) fn tiling_10(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandP
     let v2 = constructor.get_vector(21, 29);
 
     continue_tiling_by_translation(&mut constructor.tiling, (v1, v2), cuboid_hull);
-    constructor.tiling.build()
+    constructor.tiling
 }
 
-pub(super) fn tiling_11(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandPileModel {
+pub(super) fn tiling_11(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> UniformTiling {
     // 2-uniform
     // [3^3.4^2; 3^2.4.3.4]_1
     // p4g
@@ -368,10 +367,10 @@ pub(super) fn tiling_11(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandP
     let v2 = constructor.get_vector(6, 21);
 
     continue_tiling_by_translation(&mut constructor.tiling, (v1, v2), cuboid_hull);
-    constructor.tiling.build()
+    constructor.tiling
 }
 
-pub(super) fn tiling_12(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandPileModel {
+pub(super) fn tiling_12(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> UniformTiling {
     // 2-uniform
     // [3^6; 3^4.6]_1
     // p6m
@@ -390,10 +389,10 @@ pub(super) fn tiling_12(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandP
     let v2 = constructor.get_vector(12, 7);
 
     continue_tiling_by_translation(&mut constructor.tiling, (v1, v2), cuboid_hull);
-    constructor.tiling.build()
+    constructor.tiling
 }
 
-pub(super) fn tiling_13(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandPileModel {
+pub(super) fn tiling_13(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> UniformTiling {
     // 2-uniform
     // [3^6; 3^4.6]_2
     // p6
@@ -418,10 +417,10 @@ pub(super) fn tiling_13(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandP
     let v2 = constructor.get_vector(13, 20);
 
     continue_tiling_by_translation(&mut constructor.tiling, (v1, v2), cuboid_hull);
-    constructor.tiling.build()
+    constructor.tiling
 }
 
-pub(super) fn tiling_14(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandPileModel {
+pub(super) fn tiling_14(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> UniformTiling {
     // 3-uniform
     // [3.4^2.6; 3.6.3.6; 4^4]_3
     // pmm
@@ -446,6 +445,6 @@ pub(super) fn tiling_14(cuboid_hull: &Cuboid, rotate_in_degrees: usize) -> SandP
     let v2 = constructor.get_vector(9, 10);
 
     continue_tiling_by_translation(&mut constructor.tiling, (v1, v2), cuboid_hull);
-    constructor.tiling.build()
+    constructor.tiling
 }
 
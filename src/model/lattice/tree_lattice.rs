@@ -0,0 +1,87 @@
+use super::Lattice;
+
+use std::f32::consts::PI;
+use model::SandPileModel;
+use model::region::Cuboid;
+use model::sand_graph::{SandGraph, NodeIndex};
+use model::embedding::{ EmbeddingToR3, Figure };
+
+
+// Rooted tree where root has root_children children and every other inner node has branching children.
+// Leaves get edges to sink instead of missing children, so all non root nodes have equal degree.
+pub struct TreeLattice {
+    root_children: usize,
+    branching: usize,
+    depth: usize,
+}
+
+impl TreeLattice {
+    // b-ary tree: every inner node (including root) has b children
+    pub fn b_ary(branching: usize, depth: usize) -> Self {
+        assert!(branching > 0, "tree have to have at least one child per node");
+        TreeLattice { root_children: branching, branching, depth }
+    }
+
+    // Bethe lattice: every inner node (including root) has coordination_number neighbours
+    pub fn bethe(coordination_number: usize, depth: usize) -> Self {
+        assert!(coordination_number > 1, "Bethe lattice coordination number have to be at least 2");
+        TreeLattice { root_children: coordination_number, branching: coordination_number - 1, depth }
+    }
+
+    fn children_count(&self, level: usize) -> usize {
+        if level == 0 { self.root_children } else { self.branching }
+    }
+}
+
+impl Lattice for TreeLattice {
+    // Radial layout: root in center of hull, level k on circle of radius k*step,
+    // every node gets an angular sector of its parent divided equally between siblings.
+    fn get_lattice(&self, cuboid_hull: &Cuboid) -> SandPileModel {
+        let [x_size, y_size, _] = *cuboid_hull;
+        let center = [x_size / 2.0, y_size / 2.0, 0.0];
+        let step = x_size.min(y_size) / (2.0 * (self.depth as f32 + 1.0));
+
+        let mut sand_graph = SandGraph::new();
+        let mut embedding = EmbeddingToR3::new();
+
+        let root_idx = sand_graph.add_node();
+        let root_figure = embedding.add_figure(Figure::polygon_on_circle(0.3 * step, 8, 0.0, 45.0));
+        embedding.set_node_info(root_idx, center, root_figure);
+        // (node, sector start, sector size) of every node on current level
+        let mut level_nodes: Vec<(NodeIndex, f32, f32)> = vec![(root_idx, 0.0, 2.0 * PI)];
+
+        for level in 1..=self.depth {
+            let children_count = self.children_count(level - 1);
+            let radius = step * level as f32;
+            let sector = 2.0 * PI / (level_nodes.len() * children_count) as f32;
+            let figure_radius = (0.3 * step).min(0.4 * radius * sector);
+            let figure_idx = embedding.add_figure(Figure::polygon_on_circle(figure_radius, 8, 0.0, 45.0));
+
+            let mut next_level_nodes: Vec<(NodeIndex, f32, f32)> = Vec::new();
+            for (parent_idx, parent_start, parent_sector) in &level_nodes {
+                let child_sector = parent_sector / children_count as f32;
+
+                for i in 0..children_count {
+                    let child_idx = sand_graph.add_node();
+                    let child_start = parent_start + child_sector * i as f32;
+                    let angle = child_start + child_sector / 2.0;
+                    let coords = [center[0] + radius * angle.cos(), center[1] + radius * angle.sin(), 0.0];
+                    embedding.set_node_info(child_idx, coords, figure_idx);
+
+                    sand_graph.add_edge(*parent_idx, child_idx, 1);
+                    sand_graph.add_edge(child_idx, *parent_idx, 1);
+
+                    next_level_nodes.push((child_idx, child_start, child_sector));
+                }
+            }
+            level_nodes = next_level_nodes;
+        }
+
+        let leaves_sink_weight = self.children_count(self.depth) as i32;
+        for (leaf_idx, _, _) in &level_nodes {
+            sand_graph.add_edge(*leaf_idx, SandGraph::SINK_NODE, leaves_sink_weight);
+        }
+
+        SandPileModel { graph: sand_graph, embedding }
+    }
+}
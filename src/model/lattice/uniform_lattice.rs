@@ -1,4 +1,5 @@
-use super::utils::{UniformTiling, FigureGeometricInfo, FULL_CIRCLE, Constructor, continue_tiling_by_translation};
+use super::utils::{UniformTiling, FigureGeometricInfo, FULL_CIRCLE, Constructor, continue_tiling_by_translation,
+                   TilingGraph, TilingOptions};
 use super::Lattice;
 use super::tilings::*;
 
@@ -10,31 +11,103 @@ use model::SandPileModel;
 #[derive(Debug)]
 pub struct SemiRegularLattice {
     tiling_code: Vec<usize>,
+    options: TilingOptions,
 }
 
 pub struct KUniformLattice {
-
+    options: TilingOptions,
 }
 
 pub struct TetrahedralOctahedral {
 }
 
 impl SemiRegularLattice {
-    pub fn new(tiling_code: Vec<usize>) -> Self {
-        let angle: usize = tiling_code.iter().map(|n| FULL_CIRCLE / 2 - FULL_CIRCLE / n).sum();
-        assert_eq!(angle % FULL_CIRCLE, 0,
-                   "Incorrect tiling code {:#?}. Figures don't sum up to {} degrees", tiling_code, FULL_CIRCLE);
+    // vertex configurations of all 11 Archimedean (1-uniform) tilings
+    pub const ARCHIMEDEAN_CODES: [&'static [usize]; 11] = [
+        &[3, 3, 3, 3, 3, 3],
+        &[4, 4, 4, 4],
+        &[6, 6, 6],
+        &[3, 3, 3, 3, 6],
+        &[3, 3, 3, 4, 4],
+        &[3, 3, 4, 3, 4],
+        &[3, 4, 6, 4],
+        &[3, 6, 3, 6],
+        &[3, 12, 12],
+        &[4, 6, 12],
+        &[4, 8, 8],
+    ];
+
+    // snub hexagonal tiling, vertex neighbourhood doesn't define where hexagons go
+    const CHIRAL_CODE: &'static [usize] = &[3, 3, 3, 3, 6];
+
+    pub fn new(tiling_code: Vec<usize>) -> Result<Self, String> {
+        if tiling_code.len() < 3 {
+            return Err(format!("Incorrect tiling code {:?}. At least 3 figures have to meet in a vertex", tiling_code));
+        }
+        if let Some(sides_count) = tiling_code.iter().find(|n| **n < 3) {
+            return Err(format!("Incorrect tiling code {:?}. Figure with {} sides is not a polygon",
+                               tiling_code, sides_count));
+        }
+
+        let angle: f32 = tiling_code.iter().map(|n| 180.0 - 360.0 / (*n as f32)).sum();
+        if (angle - 360.0).abs() > 0.001 {
+            return Err(format!("Incorrect tiling code {:?}. Figures don't sum up to 360 degrees", tiling_code));
+        }
+
+        if Self::find_archimedean_code(&tiling_code).is_none() {
+            return Err(format!("Incorrect tiling code {:?}. Figures fit around one vertex, but can't tile the plane \
+                                with all vertices alike. Possible codes are {:?} up to rotation and reflection",
+                               tiling_code, Self::ARCHIMEDEAN_CODES));
+        }
+
+        Ok(SemiRegularLattice { tiling_code, options: TilingOptions::new() })
+    }
+
+    pub fn with_graph(mut self, graph: TilingGraph) -> Self {
+        self.options.graph = graph;
+        self
+    }
+
+    pub fn with_options(mut self, options: TilingOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    fn is_same_code_up_to_symmetry(code: &Vec<usize>, other: &[usize]) -> bool {
+        if code.len() != other.len() {
+            return false;
+        }
+        let len = code.len();
+        (0..len).any(|shift| {
+            (0..len).all(|i| code[(i + shift) % len] == other[i]) ||
+                (0..len).all(|i| code[(len + shift - i) % len] == other[i])
+        })
+    }
+
+    fn find_archimedean_code(tiling_code: &Vec<usize>) -> Option<&'static [usize]> {
+        Self::ARCHIMEDEAN_CODES
+            .iter()
+            .find(|code| Self::is_same_code_up_to_symmetry(tiling_code, code))
+            .map(|code| *code)
+    }
 
-        SemiRegularLattice { tiling_code }
+    fn is_chiral(&self) -> bool {
+        Self::is_same_code_up_to_symmetry(&self.tiling_code, Self::CHIRAL_CODE)
     }
 }
 
 impl Lattice for SemiRegularLattice {
     fn get_lattice(&self, cuboid_hull: &Cuboid) -> SandPileModel {
+        if self.is_chiral() {
+            // growing by vertex rules stalls, build it by translations instead
+            return tiling_1(cuboid_hull, 0).with_options(self.options).build();
+        }
+
         let side_size = 1.0_f32;
         let [x_size, y_size, _] = *cuboid_hull;
 
         let mut tiling_builder = UniformTiling::new(1.0);
+        tiling_builder.options = self.options;
 
         // add figures from self.tiling_code around Origin = [x_size/2, y_size/2, 0]
         let mut rotate = 0;
@@ -84,13 +157,23 @@ impl Lattice for SemiRegularLattice {
 
 impl KUniformLattice {
     pub fn new() -> Self {
-        KUniformLattice {}
+        KUniformLattice { options: TilingOptions::new() }
+    }
+
+    pub fn with_graph(mut self, graph: TilingGraph) -> Self {
+        self.options.graph = graph;
+        self
+    }
+
+    pub fn with_options(mut self, options: TilingOptions) -> Self {
+        self.options = options;
+        self
     }
 }
 
 impl Lattice for KUniformLattice {
     fn get_lattice(&self, cuboid_hull: &Cuboid) -> SandPileModel {
-        tiling_3(cuboid_hull, 90)
+        tiling_3(cuboid_hull, 90).with_options(self.options).build()
     }
 }
 
@@ -37,11 +37,47 @@ pub(super) struct VertexFigures {
 use std::collections::{HashMap, HashSet};
 use std::sync::mpsc::TrySendError::Full;
 
+// Which graph is built from a tiling: polygons are nodes connected across shared vertices,
+// or tiling vertices are nodes connected by polygon sides
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TilingGraph {
+    Faces,
+    Vertices,
+}
+
+// Which faces are neighbours in faces graph
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Neighbourhood {
+    // faces with common side
+    Sides,
+    // faces with common side or common corner
+    Moore,
+}
+
+// Weight of edge between two faces in faces graph, multiplied by TilingOptions::weight_scale and rounded
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EdgeWeighting {
+    Unit,
+    // length of common side, faces touching only at a corner get weight 1
+    SideLength,
+    // distance between centers of faces
+    CenterDistance,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TilingOptions {
+    pub graph: TilingGraph,
+    pub neighbourhood: Neighbourhood,
+    pub edge_weighting: EdgeWeighting,
+    pub weight_scale: f32,
+}
+
 #[derive(Debug)]
 pub(super) struct UniformTiling {
     pub side_size: f32,
     pub figures: DataOnPlane<FigureGeometricInfo>,
     pub vertices_info: DataOnPlane<VertexFigures>,
+    pub options: TilingOptions,
 }
 
 pub(super) struct Constructor {
@@ -110,6 +146,42 @@ impl VertexFigures {
         Regex::new(&regex_str).unwrap()
     }
 
+    // Sides counts of figures in order of angle
+    fn code(&self) -> Vec<usize> {
+        self.figures.iter().map(|figure| figure.sides_count).collect()
+    }
+
+    // Number of figures around the vertex when it is complete, found by matching its figures
+    // against codes of complete vertices up to rotation and reflection. Largest one if several codes fit
+    fn full_figures_count(&self, complete_codes: &HashSet<Vec<usize>>) -> Option<usize> {
+        complete_codes
+            .iter()
+            .filter(|complete_code| {
+                let mut code = (*complete_code).clone();
+                (0..2).any(|_| {
+                    code.reverse();
+                    (0..code.len()).any(|_| {
+                        let last = code.pop().unwrap();
+                        code.insert(0, last);
+                        self.fits(&code)
+                    })
+                })
+            })
+            .map(|complete_code| complete_code.len())
+            .max()
+    }
+
+    // Code placed from the angle of the first figure has every figure of the vertex at its angle
+    fn fits(&self, code: &Vec<usize>) -> bool {
+        let mut code_angles = HashMap::new();
+        let mut angle = self.figures[0].angle;
+        for sides_count in code {
+            code_angles.insert(angle % FULL_CIRCLE, *sides_count);
+            angle += FULL_CIRCLE / 2 - FULL_CIRCLE / sides_count;
+        }
+        self.figures.iter().all(|figure| code_angles.get(&(figure.angle % FULL_CIRCLE)) == Some(&figure.sides_count))
+    }
+
     pub fn new_figures(&mut self, tiling_code: &Vec<usize>) -> Vec<(usize, usize)> {
         if self.figures.len() == tiling_code.len() || ! self.check_for_new_figures {
             return Vec::new();
@@ -231,12 +303,27 @@ impl FigureGeometricInfo {
 }
 
 
+impl TilingOptions {
+    pub fn new() -> Self {
+        TilingOptions {
+            graph: TilingGraph::Faces,
+            neighbourhood: Neighbourhood::Sides,
+            edge_weighting: EdgeWeighting::Unit,
+            weight_scale: 1.0,
+        }
+    }
+
+    fn quantize(&self, weight: f32) -> i32 {
+        ((weight * self.weight_scale).round() as i32).max(1)
+    }
+}
+
 impl UniformTiling {
     pub fn new(side_size: f32) -> Self {
         let vertices_info: DataOnPlane<VertexFigures> = DataOnPlane::new();
         let figures: DataOnPlane<FigureGeometricInfo> = DataOnPlane::new();
 
-        UniformTiling { vertices_info, figures, side_size }
+        UniformTiling { vertices_info, figures, side_size, options: TilingOptions::new() }
     }
 
     pub fn add_figure(&mut self, figure: FigureGeometricInfo) -> usize {
@@ -269,7 +356,19 @@ impl UniformTiling {
         }
     }
 
+    pub fn with_options(mut self, options: TilingOptions) -> Self {
+        self.options = options;
+        self
+    }
+
     pub fn build(&self) -> SandPileModel {
+        match self.options.graph {
+            TilingGraph::Faces => self.build_faces_graph(),
+            TilingGraph::Vertices => self.build_vertices_graph(),
+        }
+    }
+
+    fn build_faces_graph(&self) -> SandPileModel {
         let mut sand_graph = SandGraph::new();
         let mut embedding = EmbeddingToR3::new();
 
@@ -307,7 +406,8 @@ impl UniformTiling {
 
         // add all edges
 
-        let mut node_neighbours: Vec<HashSet<NodeIndex>> = vec![HashSet::new(); sand_graph.nodes.len()];
+        // neighbour -> is there common side
+        let mut node_neighbours: Vec<HashMap<NodeIndex, bool>> = vec![HashMap::new(); sand_graph.nodes.len()];
 
 
         for (_, node_figures) in &self.vertices_info.data {
@@ -324,24 +424,158 @@ impl UniformTiling {
                 let node_1_idx = figure_idx_to_node_idx[figure_node_info_1.figure_idx];
                 let node_2_idx = figure_idx_to_node_idx[figure_node_info_2.figure_idx];
 
-                node_neighbours[node_1_idx].insert(node_2_idx);
-                node_neighbours[node_2_idx].insert(node_1_idx);
+                node_neighbours[node_1_idx].insert(node_2_idx, true);
+                node_neighbours[node_2_idx].insert(node_1_idx, true);
+
+                if self.options.neighbourhood == Neighbourhood::Moore {
+                    // figures which are not consecutive around vertex touch only at this corner
+                    for j in (i + 2)..figures_count {
+                        if i == 0 && j == figures_count - 1 {
+                            continue;
+                        }
+                        let node_3_idx = figure_idx_to_node_idx[node_figures.figures[j].figure_idx];
+                        node_neighbours[node_1_idx].entry(node_3_idx).or_insert(false);
+                        node_neighbours[node_3_idx].entry(node_1_idx).or_insert(false);
+                    }
+                }
             }
         }
 
+        let complete_codes = self.complete_codes();
         for node_idx in sand_graph.non_sink_nodes() {
-            let sides_count = self.figures.data[node_idx - 1].1.sides_count;
+            let (_, figure) = &self.figures.data[node_idx - 1];
+
+            let mut sides_neighbours_count = 0;
+            for (neighbour_idx, is_common_side) in &node_neighbours[node_idx] {
+                let (_, neighbour_figure) = &self.figures.data[*neighbour_idx - 1];
+                let weight = self.edge_weight(figure, neighbour_figure, *is_common_side);
+                sand_graph.add_edge(node_idx, *neighbour_idx, weight);
+                if *is_common_side {
+                    sides_neighbours_count += 1;
+                }
+            }
 
+            let sink_weight = self.sink_weight(figure, sides_neighbours_count, &complete_codes);
+            if sink_weight > 0 {
+                sand_graph.add_edge(node_idx, SandGraph::SINK_NODE, sink_weight);
+            }
+        }
+
+
+        SandPileModel { graph: sand_graph, embedding}
+    }
+
+    fn edge_weight(&self, figure_1: &FigureGeometricInfo, figure_2: &FigureGeometricInfo, is_common_side: bool) -> i32 {
+        match self.options.edge_weighting {
+            EdgeWeighting::Unit => 1,
+            EdgeWeighting::SideLength if is_common_side => self.options.quantize(self.side_size),
+            EdgeWeighting::SideLength => 1,
+            EdgeWeighting::CenterDistance =>
+                self.options.quantize(vecmath::vec3_len(vecmath::vec3_sub(figure_1.center, figure_2.center))),
+        }
+    }
+
+    // Figures outside of tiling are unknown, so every missing side neighbour is taken as a copy of figure
+    // reflected through the side. With Moore neighbourhood every incomplete vertex also misses its corner
+    // neighbours, all figures of the complete vertex of its type except the figure and its 2 side neighbours.
+    fn sink_weight(&self, figure: &FigureGeometricInfo, sides_neighbours_count: usize,
+                   complete_codes: &HashSet<Vec<usize>>) -> i32 {
+        let inradius = figure.r * (to_radians(figure.alpha) / 2.0).sin();
+        let missing_sides_count = figure.sides_count.saturating_sub(sides_neighbours_count) as i32;
+        let side_weight = match self.options.edge_weighting {
+            EdgeWeighting::Unit => 1,
+            EdgeWeighting::SideLength => self.options.quantize(self.side_size),
+            EdgeWeighting::CenterDistance => self.options.quantize(2.0 * inradius),
+        };
+        let mut weight = missing_sides_count * side_weight;
+
+        if self.options.neighbourhood == Neighbourhood::Moore {
+            let largest_figures_count = complete_codes.iter().map(|code| code.len()).max().unwrap_or(0);
+            let missing_corners_count: usize = figure.vertices
+                .iter()
+                .map(|(pos, _)| {
+                    let vertex_idx = self.vertices_info.get_point_by_coords(*pos).unwrap();
+                    &self.vertices_info.data[vertex_idx].1
+                })
+                .filter(|vertex_figures| ! vertex_figures.is_complete())
+                .map(|vertex_figures| {
+                    let figures_count = vertex_figures.full_figures_count(complete_codes).unwrap_or(largest_figures_count);
+                    figures_count.saturating_sub(3)
+                })
+                .sum();
+            let corner_weight = match self.options.edge_weighting {
+                EdgeWeighting::CenterDistance => self.options.quantize(2.0 * figure.r),
+                _ => 1,
+            };
+            weight += missing_corners_count as i32 * corner_weight;
+        }
+
+        weight
+    }
+
+    // Codes of all vertex types which appear complete in the tiling
+    fn complete_codes(&self) -> HashSet<Vec<usize>> {
+        self.vertices_info.data
+            .iter()
+            .filter(|(_, vertex_figures)| vertex_figures.is_complete())
+            .map(|(_, vertex_figures)| vertex_figures.code())
+            .collect()
+    }
+
+    fn build_vertices_graph(&self) -> SandPileModel {
+        let mut sand_graph = SandGraph::new();
+        let mut embedding = EmbeddingToR3::new();
+
+        let vertex_figure = Figure::polygon_on_circle(0.2 * self.side_size, 8, 0.0, 45.0);
+        let vertex_figure_idx = embedding.add_figure(vertex_figure);
+
+        // vertex idx in self.vertices_info is node_idx - 1
+        for (pos, _) in &self.vertices_info.data {
+            let node_idx = sand_graph.add_node();
+            embedding.set_node_info(node_idx, *pos, vertex_figure_idx);
+        }
+
+        // every side belongs to two figures, so keep neighbours in order of appearance without repeats
+        let mut node_neighbours: Vec<Vec<NodeIndex>> = vec![Vec::new(); sand_graph.nodes.len()];
+        let mut added_sides: HashSet<(NodeIndex, NodeIndex)> = HashSet::new();
+
+        for (_, figure) in &self.figures.data {
+            let vertices_count = figure.vertices.len();
+            for i in 0..vertices_count {
+                let (pos_1, _) = figure.vertices[i];
+                let (pos_2, _) = figure.vertices[(i + 1) % vertices_count];
+                let node_1_idx = self.vertices_info.get_point_by_coords(pos_1).unwrap() + 1;
+                let node_2_idx = self.vertices_info.get_point_by_coords(pos_2).unwrap() + 1;
+
+                let side = (node_1_idx.min(node_2_idx), node_1_idx.max(node_2_idx));
+                if added_sides.insert(side) {
+                    node_neighbours[node_1_idx].push(node_2_idx);
+                    node_neighbours[node_2_idx].push(node_1_idx);
+                }
+            }
+        }
+
+        // complete vertex has as many sides as figures around it, incomplete ones are topped up
+        // by edges to sink to the degree of complete vertex of their own type
+        let complete_codes = self.complete_codes();
+        let largest_degree = node_neighbours.iter().map(|n| n.len()).max().unwrap_or(0);
+
+        for node_idx in sand_graph.non_sink_nodes() {
             for neighbour_idx in &node_neighbours[node_idx] {
                 sand_graph.add_edge(node_idx, *neighbour_idx, 1);
             }
-            if node_neighbours[node_idx].len() < sides_count {
+
+            let (_, vertex_figures) = &self.vertices_info.data[node_idx - 1];
+            if vertex_figures.is_complete() {
+                continue;
+            }
+            let full_degree = vertex_figures.full_figures_count(&complete_codes).unwrap_or(largest_degree);
+            if node_neighbours[node_idx].len() < full_degree {
                 sand_graph.add_edge(node_idx, SandGraph::SINK_NODE,
-                                    (sides_count - node_neighbours[node_idx].len()) as i32);
+                                    (full_degree - node_neighbours[node_idx].len()) as i32);
             }
         }
 
-
         SandPileModel { graph: sand_graph, embedding}
     }
 }
@@ -0,0 +1,65 @@
+use graphics::math;
+use rand::Rng;
+use super::region::Cuboid;
+use super::sand_graph::SandGraph;
+
+pub const FORCE_DIRECTED_ITERATIONS: usize = 100;
+
+// Fruchterman-Reingold layout inside [0, x_size] x [0, y_size], edges to sink are ignored.
+// Result is indexed by node index, sink coordinates are left zero.
+pub fn force_directed<R: Rng>(graph: &SandGraph, cuboid_hull: &Cuboid, rng: &mut R) -> Vec<math::Vec3d<f32>> {
+    let [x_size, y_size, _] = *cuboid_hull;
+    let nodes_count = graph.nodes.len();
+    let mut positions: Vec<[f32; 2]> = vec![[0.0; 2]; nodes_count];
+
+    for node_idx in graph.non_sink_nodes() {
+        positions[node_idx] = [rng.gen::<f32>() * x_size, rng.gen::<f32>() * y_size];
+    }
+
+    // optimal distance between nodes, smaller than area per node so that walls don't collect nodes
+    let k = 0.5 * (x_size * y_size / (nodes_count.max(2) - 1) as f32).sqrt();
+    let center = [x_size / 2.0, y_size / 2.0];
+
+    for iteration in 0..FORCE_DIRECTED_ITERATIONS {
+        let temperature = x_size.max(y_size) / 10.0 * (1.0 - iteration as f32 / FORCE_DIRECTED_ITERATIONS as f32);
+        let mut displacement: Vec<[f32; 2]> = vec![[0.0; 2]; nodes_count];
+
+        for v in graph.non_sink_nodes() {
+            for u in (v + 1)..nodes_count {
+                let delta = [positions[v][0] - positions[u][0], positions[v][1] - positions[u][1]];
+                let distance = (delta[0] * delta[0] + delta[1] * delta[1]).sqrt().max(0.01);
+                let force = k * k / distance;
+                for i in 0..2 {
+                    displacement[v][i] += delta[i] / distance * force;
+                    displacement[u][i] -= delta[i] / distance * force;
+                }
+            }
+
+            for (_, u) in graph.successors(v) {
+                if u == SandGraph::SINK_NODE || u == v {
+                    continue;
+                }
+                let delta = [positions[v][0] - positions[u][0], positions[v][1] - positions[u][1]];
+                let distance = (delta[0] * delta[0] + delta[1] * delta[1]).sqrt().max(0.01);
+                // every undirected edge is seen from both ends, so each end pulls with half of the force
+                let force = distance * distance / k / 2.0;
+                for i in 0..2 {
+                    displacement[v][i] -= delta[i] / distance * force;
+                    displacement[u][i] += delta[i] / distance * force;
+                }
+            }
+        }
+
+        for node_idx in graph.non_sink_nodes() {
+            // weak gravity keeps components which are not connected together
+            let [dx, dy] = displacement[node_idx];
+            let (dx, dy) = (dx + (center[0] - positions[node_idx][0]) / k, dy + (center[1] - positions[node_idx][1]) / k);
+            let length = (dx * dx + dy * dy).sqrt().max(0.01);
+            let step = length.min(temperature);
+            positions[node_idx][0] = (positions[node_idx][0] + dx / length * step).max(0.0).min(x_size);
+            positions[node_idx][1] = (positions[node_idx][1] + dy / length * step).max(0.0).min(y_size);
+        }
+    }
+
+    positions.iter().map(|[x, y]| [*x, *y, 0.0]).collect()
+}
@@ -2,6 +2,15 @@ pub mod embedding;
 pub mod sand_graph;
 pub mod lattice;
 pub mod region;
+pub mod import;
+pub mod layout;
+pub mod divisor;
+pub mod sandpile_group;
+pub mod spanning_tree;
+mod product;
+mod disorder;
+
+pub use self::disorder::WeightDistribution;
 
 use self::embedding::EmbeddingToR3;
 use self::sand_graph::{SandGraph};
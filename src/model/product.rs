@@ -0,0 +1,118 @@
+use graphics::math;
+use super::SandPileModel;
+use super::sand_graph::{SandGraph, NodeIndex};
+use super::embedding::{EmbeddingToR3, Figure};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ProductKind {
+    Cartesian,
+    Tensor,
+}
+
+impl SandPileModel {
+    // (u, v) -> (u', v) with weight of u -> u' and (u, v) -> (u, v') with weight of v -> v'.
+    // Edge to sink in either factor goes to sink, so degree of (u, v) is deg u + deg v.
+    pub fn cartesian_product(&self, other: &SandPileModel) -> SandPileModel {
+        self.product(other, ProductKind::Cartesian)
+    }
+
+    // (u, v) -> (u', v') with weight w(u -> u') * w(v -> v').
+    // Edge goes to sink if u' or v' is sink, so degree of (u, v) is deg u * deg v.
+    pub fn tensor_product(&self, other: &SandPileModel) -> SandPileModel {
+        self.product(other, ProductKind::Tensor)
+    }
+
+    fn product(&self, other: &SandPileModel, kind: ProductKind) -> SandPileModel {
+        let other_nodes_count = other.graph.nodes.len() - 1;
+        let pair_to_index = |u: NodeIndex, v: NodeIndex| {
+            if u == SandGraph::SINK_NODE || v == SandGraph::SINK_NODE {
+                SandGraph::SINK_NODE
+            } else {
+                (u - 1) * other_nodes_count + v
+            }
+        };
+
+        let mut sand_graph = SandGraph::new();
+        for _ in self.graph.non_sink_nodes() {
+            for _ in other.graph.non_sink_nodes() {
+                sand_graph.add_node();
+            }
+        }
+
+        for u in self.graph.non_sink_nodes() {
+            for v in other.graph.non_sink_nodes() {
+                let node_idx = pair_to_index(u, v);
+                match kind {
+                    ProductKind::Cartesian => {
+                        for (weight, u_neighbour) in self.graph.successors(u) {
+                            sand_graph.add_edge(node_idx, pair_to_index(u_neighbour, v), weight);
+                        }
+                        for (weight, v_neighbour) in other.graph.successors(v) {
+                            sand_graph.add_edge(node_idx, pair_to_index(u, v_neighbour), weight);
+                        }
+                    },
+                    ProductKind::Tensor => {
+                        for (u_weight, u_neighbour) in self.graph.successors(u) {
+                            for (v_weight, v_neighbour) in other.graph.successors(v) {
+                                sand_graph.add_edge(node_idx, pair_to_index(u_neighbour, v_neighbour),
+                                                    u_weight * v_weight);
+                            }
+                        }
+                    },
+                }
+            }
+        }
+
+        let embedding = self.product_embedding(other);
+        SandPileModel { graph: sand_graph, embedding }
+    }
+
+    // Every node u of self is replaced by a small copy of other, which fits between u and its nearest neighbour
+    fn product_embedding(&self, other: &SandPileModel) -> EmbeddingToR3 {
+        let mut min_edge_length = std::f32::MAX;
+        for u in self.graph.non_sink_nodes() {
+            let (u_coords, _) = self.embedding.get_node_info(u);
+            for (_, neighbour) in self.graph.successors(u) {
+                if neighbour != SandGraph::SINK_NODE && neighbour != u {
+                    let (neighbour_coords, _) = self.embedding.get_node_info(neighbour);
+                    let length = vecmath::vec3_len(vecmath::vec3_sub(u_coords, neighbour_coords));
+                    if length > 0.0 {
+                        min_edge_length = min_edge_length.min(length);
+                    }
+                }
+            }
+        }
+        if min_edge_length == std::f32::MAX {
+            min_edge_length = 1.0;
+        }
+
+        let other_nodes_count = other.graph.nodes.len() - 1;
+        let mut other_center: math::Vec3d<f32> = [0.0; 3];
+        for v in other.graph.non_sink_nodes() {
+            let (v_coords, _) = other.embedding.get_node_info(v);
+            other_center = vecmath::vec3_add(other_center, vecmath::vec3_scale(v_coords, 1.0 / other_nodes_count as f32));
+        }
+        let other_radius = other.graph.non_sink_nodes()
+            .map(|v| vecmath::vec3_len(vecmath::vec3_sub(other.embedding.get_node_info(v).0, other_center)))
+            .fold(0.0_f32, f32::max);
+        let scale = if other_radius > 0.0 { 0.4 * min_edge_length / other_radius } else { 1.0 };
+
+        let mut embedding = EmbeddingToR3::new();
+        for figure in &other.embedding.unique_figures {
+            let vertices: Vec<math::Vec3d<f32>> = figure.vertices.iter().map(|v| vecmath::vec3_scale(*v, scale)).collect();
+            embedding.add_figure(Figure { vertices, indexes: figure.indexes.clone(), border_indexes: figure.border_indexes.clone() });
+        }
+
+        for u in self.graph.non_sink_nodes() {
+            let (u_coords, _) = self.embedding.get_node_info(u);
+            for v in other.graph.non_sink_nodes() {
+                let (v_coords, figure_idx) = other.embedding.get_node_info(v);
+                let shift = vecmath::vec3_scale(vecmath::vec3_sub(v_coords, other_center), scale);
+                let node_idx = (u - 1) * other_nodes_count + v;
+                embedding.set_node_info(node_idx, vecmath::vec3_add(u_coords, shift), figure_idx);
+            }
+        }
+
+        embedding
+    }
+}
@@ -72,6 +72,21 @@ impl SandGraph {
         node_data.degree += weight;
     }
 
+    // f(source, target, weight) -> new weight, degrees are recomputed
+    pub fn map_edge_weights(&mut self, f: &mut FnMut(NodeIndex, NodeIndex, i32) -> i32) {
+        for source in 0..self.nodes.len() {
+            let mut degree = 0;
+            let mut current_edge_index = self.nodes[source].first_outgoing_edge;
+            while let Some(edge_index) = current_edge_index {
+                let edge = &mut self.edges[edge_index];
+                edge.weight = f(source, edge.target, edge.weight);
+                degree += edge.weight;
+                current_edge_index = edge.next_outgoing_edge;
+            }
+            self.nodes[source].degree = degree;
+        }
+    }
+
     pub fn successors(&self, source: NodeIndex) -> Successors {
         let first_outgoing_edge = self.nodes[source].first_outgoing_edge;
         Successors { graph: self, current_edge_index: first_outgoing_edge }
@@ -81,6 +96,36 @@ impl SandGraph {
     pub fn non_sink_nodes(&self) -> impl Iterator<Item = NodeIndex> {
         1 .. self.nodes.len()
     }
+
+    // Sand can leave every node, otherwise some avalanches never stop.
+    // It also means that spanning tree oriented to sink exists
+    pub fn check_sink_reachable(&self) -> Result<(), String> {
+        let mut predecessors = vec![Vec::new(); self.nodes.len()];
+        for node_idx in self.non_sink_nodes() {
+            for (weight, neighbour_node_idx) in self.successors(node_idx) {
+                if weight > 0 {
+                    predecessors[neighbour_node_idx].push(node_idx);
+                }
+            }
+        }
+
+        let mut is_reached = vec![false; self.nodes.len()];
+        is_reached[SandGraph::SINK_NODE] = true;
+        let mut stack = vec![SandGraph::SINK_NODE];
+        while let Some(node_idx) = stack.pop() {
+            for predecessor_node_idx in &predecessors[node_idx] {
+                if ! is_reached[*predecessor_node_idx] {
+                    is_reached[*predecessor_node_idx] = true;
+                    stack.push(*predecessor_node_idx);
+                }
+            }
+        }
+
+        match self.non_sink_nodes().find(|node_idx| ! is_reached[*node_idx]) {
+            Some(node_idx) => Err(format!("sink can't be reached from node {}", node_idx)),
+            None => Ok(()),
+        }
+    }
 }
 
 
@@ -0,0 +1,289 @@
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{Zero, One, Signed};
+use super::sand_graph::{SandGraph, NodeIndex};
+
+// Sandpile group is the cokernel Z^n / Z^n L of the reduced Laplacian L of non-sink nodes.
+// Its invariant factors are the diagonal of the Smith normal form of L, all computations are exact.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SandpileGroup {
+    // invariant factors greater than 1, every one divides the next one, empty for the trivial group
+    pub invariant_factors: Vec<BigInt>,
+}
+
+impl SandpileGroup {
+    pub fn new(graph: &SandGraph) -> Self {
+        let diagonal = smith_normal_form(reduced_laplacian(graph));
+        let invariant_factors = diagonal.into_iter().filter(|d| ! d.is_one()).collect();
+        SandpileGroup { invariant_factors }
+    }
+
+    pub fn order(&self) -> BigInt {
+        self.invariant_factors.iter().fold(BigInt::one(), |order, d| order * d)
+    }
+
+    pub fn rank(&self) -> usize {
+        self.invariant_factors.len()
+    }
+
+    // Matrix-tree theorem: group order is the number of spanning trees oriented to sink
+    pub fn check_matrix_tree(&self, graph: &SandGraph) -> Result<(), String> {
+        let trees_count = spanning_trees_count(graph);
+        if self.order() != trees_count {
+            return Err(format!("group order {} differs from spanning trees count {}", self.order(), trees_count));
+        }
+        Ok(())
+    }
+}
+
+// Row of node v is v - 1: L[v][v] = degree(v) - loops, L[v][u] = -weight(v -> u), sink column is dropped
+pub fn reduced_laplacian(graph: &SandGraph) -> Vec<Vec<BigInt>> {
+    let size = graph.nodes.len() - 1;
+    let mut laplacian = vec![vec![BigInt::zero(); size]; size];
+    for node_idx in graph.non_sink_nodes() {
+        laplacian[node_idx - 1][node_idx - 1] += graph.nodes[node_idx].degree;
+        for (weight, neighbour_node_idx) in graph.successors(node_idx) {
+            if neighbour_node_idx != SandGraph::SINK_NODE {
+                laplacian[node_idx - 1][neighbour_node_idx - 1] -= weight;
+            }
+        }
+    }
+    laplacian
+}
+
+// Number of spanning trees oriented to sink, determinant of the reduced Laplacian by fraction-free Bareiss elimination
+pub fn spanning_trees_count(graph: &SandGraph) -> BigInt {
+    let mut matrix = reduced_laplacian(graph);
+    let size = matrix.len();
+    let mut sign = BigInt::one();
+    let mut previous_pivot = BigInt::one();
+
+    for k in 0..size {
+        let pivot_row = match (k..size).find(|i| ! matrix[*i][k].is_zero()) {
+            Some(i) => i,
+            None => return BigInt::zero(),
+        };
+        if pivot_row != k {
+            matrix.swap(pivot_row, k);
+            sign = -sign;
+        }
+        for i in (k + 1)..size {
+            for j in (k + 1)..size {
+                let value = &matrix[i][j] * &matrix[k][k] - &matrix[i][k] * &matrix[k][j];
+                matrix[i][j] = value / &previous_pivot;
+            }
+        }
+        previous_pivot = matrix[k][k].clone();
+    }
+
+    if size == 0 { BigInt::one() } else { sign * &matrix[size - 1][size - 1] }
+}
+
+// Order of the class of configuration in the sandpile group: smallest k such that k * configuration
+// is a sum of topplings. Topplings are rows of L, so it solves L^T x = configuration exactly,
+// x = y / det with integer y by Cramer's rule, and the order is the common denominator of x
+pub fn element_order(graph: &SandGraph, configuration: &Vec<i32>) -> BigInt {
+    let laplacian = reduced_laplacian(graph);
+    let size = laplacian.len();
+    // [L^T | configuration]
+    let mut matrix: Vec<Vec<BigInt>> = (0..size)
+        .map(|i| {
+            let mut row: Vec<BigInt> = (0..size).map(|j| laplacian[j][i].clone()).collect();
+            row.push(BigInt::from(configuration[i]));
+            row
+        })
+        .collect();
+
+    // Bareiss elimination, last pivot is the determinant
+    let mut previous_pivot = BigInt::one();
+    for k in 0..size {
+        let pivot_row = (k..size).find(|i| ! matrix[*i][k].is_zero())
+            .expect("reduced Laplacian is singular, some node can't reach sink");
+        matrix.swap(pivot_row, k);
+        for i in (k + 1)..size {
+            for j in (k + 1)..=size {
+                let value = &matrix[i][j] * &matrix[k][k] - &matrix[i][k] * &matrix[k][j];
+                matrix[i][j] = value / &previous_pivot;
+            }
+            matrix[i][k] = BigInt::zero();
+        }
+        previous_pivot = matrix[k][k].clone();
+    }
+    if size == 0 {
+        return BigInt::one();
+    }
+
+    // fraction-free back substitution, y = det * x
+    let det = matrix[size - 1][size - 1].clone();
+    let mut y = vec![BigInt::zero(); size];
+    for i in (0..size).rev() {
+        let mut value = &det * &matrix[i][size];
+        for j in (i + 1)..size {
+            value -= &matrix[i][j] * &y[j];
+        }
+        y[i] = value / &matrix[i][i];
+    }
+
+    let common_divisor = y.iter().fold(det.clone(), |g, y_i| g.gcd(y_i));
+    (det / common_divisor).abs()
+}
+
+// Diagonal of the Smith normal form with non-negative entries, every entry divides the next one
+pub fn smith_normal_form(mut matrix: Vec<Vec<BigInt>>) -> Vec<BigInt> {
+    let rows_count = matrix.len();
+    let columns_count = if rows_count > 0 { matrix[0].len() } else { 0 };
+    let mut diagonal = Vec::new();
+
+    for k in 0..rows_count.min(columns_count) {
+        loop {
+            // smallest non-zero entry of the remaining submatrix goes to (k, k)
+            let mut pivot: Option<(NodeIndex, NodeIndex)> = None;
+            for i in k..rows_count {
+                for j in k..columns_count {
+                    if ! matrix[i][j].is_zero() &&
+                        pivot.map_or(true, |(pi, pj)| matrix[i][j].abs() < matrix[pi][pj].abs()) {
+                        pivot = Some((i, j));
+                    }
+                }
+            }
+            let (pi, pj) = match pivot {
+                Some(p) => p,
+                None => {
+                    // the rest is zero
+                    diagonal.resize(rows_count.min(columns_count), BigInt::zero());
+                    return diagonal;
+                },
+            };
+            matrix.swap(k, pi);
+            for row in matrix.iter_mut() {
+                row.swap(k, pj);
+            }
+
+            // reduce column k and row k by the pivot, remainders are smaller than it
+            let mut is_done = true;
+            for i in (k + 1)..rows_count {
+                let q = matrix[i][k].div_floor(&matrix[k][k]);
+                if ! q.is_zero() {
+                    for j in k..columns_count {
+                        let value = &q * &matrix[k][j];
+                        matrix[i][j] -= value;
+                    }
+                }
+                is_done &= matrix[i][k].is_zero();
+            }
+            for j in (k + 1)..columns_count {
+                let q = matrix[k][j].div_floor(&matrix[k][k]);
+                if ! q.is_zero() {
+                    for i in k..rows_count {
+                        let value = &q * &matrix[i][k];
+                        matrix[i][j] -= value;
+                    }
+                }
+                is_done &= matrix[k][j].is_zero();
+            }
+            if ! is_done {
+                continue;
+            }
+
+            // pivot has to divide the rest, otherwise the row with bad entry is added to row k
+            let bad_row = (k + 1..rows_count)
+                .find(|i| (k + 1..columns_count).any(|j| ! matrix[*i][j].is_multiple_of(&matrix[k][k])));
+            match bad_row {
+                Some(i) => {
+                    for j in k..columns_count {
+                        let value = matrix[i][j].clone();
+                        matrix[k][j] += value;
+                    }
+                },
+                None => break,
+            }
+        }
+        diagonal.push(matrix[k][k].abs());
+    }
+
+    diagonal
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigInt;
+    use model::sand_graph::SandGraph;
+    use super::{SandpileGroup, spanning_trees_count, element_order, smith_normal_form};
+
+    // size x size grid, boundary nodes are topped up to degree 4 by edges to sink
+    fn grid(size: usize) -> SandGraph {
+        let mut graph = SandGraph::new();
+        for _ in 0..size * size {
+            graph.add_node();
+        }
+        let node_idx = |x: usize, y: usize| y * size + x + 1;
+        for y in 0..size {
+            for x in 0..size {
+                let mut neighbours = Vec::new();
+                if x > 0 { neighbours.push(node_idx(x - 1, y)); }
+                if x + 1 < size { neighbours.push(node_idx(x + 1, y)); }
+                if y > 0 { neighbours.push(node_idx(x, y - 1)); }
+                if y + 1 < size { neighbours.push(node_idx(x, y + 1)); }
+                for neighbour_node_idx in &neighbours {
+                    graph.add_edge(node_idx(x, y), *neighbour_node_idx, 1);
+                }
+                if neighbours.len() < 4 {
+                    graph.add_edge(node_idx(x, y), SandGraph::SINK_NODE, 4 - neighbours.len() as i32);
+                }
+            }
+        }
+        graph
+    }
+
+    // cycle of n nodes with sink as one of them
+    fn cycle(n: usize) -> SandGraph {
+        let mut graph = SandGraph::new();
+        for _ in 1..n {
+            graph.add_node();
+        }
+        for node_idx in graph.non_sink_nodes() {
+            graph.add_edge(node_idx, node_idx - 1, 1);
+            graph.add_edge(node_idx, (node_idx + 1) % n, 1);
+        }
+        graph
+    }
+
+    fn to_bigints(values: Vec<i64>) -> Vec<BigInt> {
+        values.into_iter().map(BigInt::from).collect()
+    }
+
+    #[test]
+    fn grid_order_is_trees_count() {
+        let graph = grid(4);
+        let group = SandpileGroup::new(&graph);
+        assert_eq!(group.order(), BigInt::from(557568000));
+        assert_eq!(spanning_trees_count(&graph), BigInt::from(557568000));
+        group.check_matrix_tree(&graph).unwrap();
+    }
+
+    #[test]
+    fn cycle_group_is_cyclic() {
+        for n in 2..8 {
+            let graph = cycle(n);
+            let group = SandpileGroup::new(&graph);
+            assert_eq!(group.invariant_factors, to_bigints(vec![n as i64]));
+            assert_eq!(group.rank(), 1);
+
+            let mut one_grain = vec![0; n - 1];
+            one_grain[0] = 1;
+            assert_eq!(element_order(&graph, &one_grain), BigInt::from(n));
+            assert_eq!(element_order(&graph, &vec![0; n - 1]), BigInt::from(1));
+        }
+    }
+
+    #[test]
+    fn known_smith_normal_form() {
+        let matrix = vec![
+            to_bigints(vec![2, 4, 4]),
+            to_bigints(vec![-6, 6, 12]),
+            to_bigints(vec![10, -4, -16]),
+        ];
+        assert_eq!(smith_normal_form(matrix), to_bigints(vec![2, 6, 12]));
+        assert_eq!(smith_normal_form(vec![to_bigints(vec![2, 0]), to_bigints(vec![0, 3])]), to_bigints(vec![1, 6]));
+    }
+}
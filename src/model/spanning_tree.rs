@@ -0,0 +1,271 @@
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use super::sand_graph::{SandGraph, NodeIndex};
+
+// Spanning tree oriented to sink: every non-sink node has an edge to its parent.
+// Edge of weight w is w parallel edges, copy in 0..w tells which of them is in the tree.
+// Vectors are indexed by node index, sink entries are unused.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SpanningTree {
+    pub parents: Vec<NodeIndex>,
+    pub copies: Vec<i32>,
+}
+
+impl SpanningTree {
+    // Wilson's algorithm: loop-erased random walks to the tree built so far, walk goes along an edge
+    // with probability weight / degree. Every tree has the same probability, parallel edges are counted separately.
+    pub fn wilson(graph: &SandGraph, seed: u64) -> Result<Self, String> {
+        graph.check_sink_reachable()?;
+        let successors = merged_successors(graph);
+        let nodes_count = graph.nodes.len();
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let mut tree = SpanningTree {
+            parents: vec![SandGraph::SINK_NODE; nodes_count],
+            copies: vec![0; nodes_count],
+        };
+        let mut is_in_tree = vec![false; nodes_count];
+        is_in_tree[SandGraph::SINK_NODE] = true;
+
+        for start_node_idx in graph.non_sink_nodes() {
+            // walk remembers the last exit from every node, which erases loops
+            let mut node_idx = start_node_idx;
+            while ! is_in_tree[node_idx] {
+                let mut grain = rng.gen_range(0, graph.nodes[node_idx].degree);
+                for (neighbour_node_idx, weight) in &successors[node_idx] {
+                    if grain < *weight {
+                        tree.parents[node_idx] = *neighbour_node_idx;
+                        tree.copies[node_idx] = grain;
+                        break;
+                    }
+                    grain -= *weight;
+                }
+                node_idx = tree.parents[node_idx];
+            }
+
+            let mut node_idx = start_node_idx;
+            while ! is_in_tree[node_idx] {
+                is_in_tree[node_idx] = true;
+                node_idx = tree.parents[node_idx];
+            }
+        }
+        Ok(tree)
+    }
+
+    // Inverse of to_superstable, burning in steps with ties broken by node index and copy.
+    // Fails if some nodes don't burn, then configuration isn't superstable
+    pub fn from_superstable(graph: &SandGraph, configuration: &Vec<i32>) -> Result<Self, String> {
+        if configuration.len() + 1 != graph.nodes.len() || configuration.iter().any(|sand| *sand < 0) {
+            return Err("configuration has to have not negative sand of every non-sink node".to_string());
+        }
+        let successors = merged_successors(graph);
+        let nodes_count = graph.nodes.len();
+        let mut tree = SpanningTree {
+            parents: vec![SandGraph::SINK_NODE; nodes_count],
+            copies: vec![0; nodes_count],
+        };
+        let mut depths: Vec<Option<usize>> = vec![None; nodes_count];
+        depths[SandGraph::SINK_NODE] = Some(0);
+
+        let mut burnt_count = 1;
+        for step in 1..nodes_count {
+            // edges to nodes burnt before previous step, then edges to nodes burnt on it decide the parent
+            let mut burnt_now = Vec::new();
+            for node_idx in graph.non_sink_nodes().filter(|node_idx| depths[*node_idx].is_none()) {
+                let mut position = configuration[node_idx - 1];
+                for (neighbour_node_idx, weight) in &successors[node_idx] {
+                    if depths[*neighbour_node_idx].map_or(false, |depth| depth + 1 < step) {
+                        position -= weight;
+                    }
+                }
+                for (neighbour_node_idx, weight) in &successors[node_idx] {
+                    if depths[*neighbour_node_idx] == Some(step - 1) {
+                        if position < *weight {
+                            tree.parents[node_idx] = *neighbour_node_idx;
+                            tree.copies[node_idx] = position;
+                            burnt_now.push(node_idx);
+                            break;
+                        }
+                        position -= weight;
+                    }
+                }
+            }
+            if burnt_now.is_empty() {
+                break;
+            }
+            burnt_count += burnt_now.len();
+            for node_idx in burnt_now {
+                depths[node_idx] = Some(step);
+            }
+        }
+
+        if burnt_count < nodes_count {
+            return Err(format!("configuration isn't superstable, {} nodes aren't burnt", nodes_count - burnt_count));
+        }
+        Ok(tree)
+    }
+
+    // (node, parent) for every non-sink node
+    pub fn edges(&self) -> Vec<(NodeIndex, NodeIndex)> {
+        (0..self.parents.len())
+            .filter(|node_idx| *node_idx != SandGraph::SINK_NODE)
+            .map(|node_idx| (node_idx, self.parents[node_idx]))
+            .collect()
+    }
+
+    // Superstable configuration of the tree, configuration is sand of non-sink nodes.
+    // Burning starts at sink, on every step node burns if it has less sand than edges to burnt nodes.
+    // Node burns one step after its parent, so its sand is number of edges to nodes burnt two or more steps before,
+    // plus position of the tree edge among edges to nodes burnt one step before in order of node index and copy.
+    pub fn to_superstable(&self, graph: &SandGraph) -> Result<Vec<i32>, String> {
+        let depths = self.depths(graph)?;
+        let successors = merged_successors(graph);
+
+        let configuration = graph.non_sink_nodes()
+            .map(|node_idx| {
+                let mut sand = self.copies[node_idx];
+                for (neighbour_node_idx, weight) in &successors[node_idx] {
+                    if depths[*neighbour_node_idx] + 1 < depths[node_idx] ||
+                        (depths[*neighbour_node_idx] + 1 == depths[node_idx] && *neighbour_node_idx < self.parents[node_idx]) {
+                        sand += weight;
+                    }
+                }
+                sand
+            })
+            .collect();
+        Ok(configuration)
+    }
+
+    // Depth of sink is 0, fails if parents have a cycle or tree edge isn't an edge of graph
+    fn depths(&self, graph: &SandGraph) -> Result<Vec<usize>, String> {
+        let successors = merged_successors(graph);
+        let nodes_count = graph.nodes.len();
+        let mut depths: Vec<Option<usize>> = vec![None; nodes_count];
+        depths[SandGraph::SINK_NODE] = Some(0);
+
+        for start_node_idx in graph.non_sink_nodes() {
+            let weight = successors[start_node_idx].iter()
+                .find(|(neighbour_node_idx, _)| *neighbour_node_idx == self.parents[start_node_idx])
+                .map_or(0, |(_, weight)| *weight);
+            if self.parents[start_node_idx] == start_node_idx || self.copies[start_node_idx] < 0 || self.copies[start_node_idx] >= weight {
+                return Err(format!("tree edge {} -> {} copy {} isn't an edge of graph",
+                                   start_node_idx, self.parents[start_node_idx], self.copies[start_node_idx]));
+            }
+
+            let mut path = Vec::new();
+            let mut node_idx = start_node_idx;
+            while depths[node_idx].is_none() {
+                if path.len() >= nodes_count {
+                    return Err(format!("tree has a cycle through node {}", start_node_idx));
+                }
+                path.push(node_idx);
+                node_idx = self.parents[node_idx];
+            }
+            let mut depth = depths[node_idx].unwrap();
+            for node_idx in path.into_iter().rev() {
+                depth += 1;
+                depths[node_idx] = Some(depth);
+            }
+        }
+        Ok(depths.into_iter().map(|d| d.unwrap()).collect())
+    }
+}
+
+// Burning maps trees to configurations of different classes, so it gives a bijection with recurrent configurations,
+// if graph without sink is undirected or has no cycles. On other directed graphs distinct trees can give equivalent configurations
+pub fn check_burning_bijection(graph: &SandGraph) -> Result<(), String> {
+    let successors = merged_successors(graph);
+    let is_symmetric = graph.non_sink_nodes().all(|node_idx| {
+        successors[node_idx].iter().all(|(neighbour_node_idx, weight)| {
+            *neighbour_node_idx == SandGraph::SINK_NODE || *neighbour_node_idx == node_idx ||
+                successors[*neighbour_node_idx].contains(&(node_idx, *weight))
+        })
+    });
+    if is_symmetric {
+        return Ok(());
+    }
+
+    // Kahn's algorithm, nodes left with incoming edges are on cycles
+    let mut in_degrees = vec![0; graph.nodes.len()];
+    for node_idx in graph.non_sink_nodes() {
+        for (neighbour_node_idx, _) in &successors[node_idx] {
+            in_degrees[*neighbour_node_idx] += 1;
+        }
+    }
+    let mut stack: Vec<NodeIndex> = graph.non_sink_nodes().filter(|node_idx| in_degrees[*node_idx] == 0).collect();
+    let mut sorted_count = 0;
+    while let Some(node_idx) = stack.pop() {
+        sorted_count += 1;
+        for (neighbour_node_idx, _) in &successors[node_idx] {
+            in_degrees[*neighbour_node_idx] -= 1;
+            if in_degrees[*neighbour_node_idx] == 0 && *neighbour_node_idx != SandGraph::SINK_NODE {
+                stack.push(*neighbour_node_idx);
+            }
+        }
+    }
+    if sorted_count < graph.nodes.len() - 1 {
+        return Err("burning bijection needs graph which is undirected or has no cycles".to_string());
+    }
+    Ok(())
+}
+
+// Outgoing edges of every node with weights of parallel edges summed up, sorted by target
+fn merged_successors(graph: &SandGraph) -> Vec<Vec<(NodeIndex, i32)>> {
+    let mut successors = vec![Vec::new(); graph.nodes.len()];
+    for node_idx in graph.non_sink_nodes() {
+        let mut edges: Vec<(NodeIndex, i32)> = graph.successors(node_idx).map(|(w, t)| (t, w)).collect();
+        edges.sort();
+        for (neighbour_node_idx, weight) in edges {
+            match successors[node_idx].last_mut() {
+                Some((last_node_idx, last_weight)) if *last_node_idx == neighbour_node_idx => *last_weight += weight,
+                _ => successors[node_idx].push((neighbour_node_idx, weight)),
+            }
+        }
+    }
+    successors
+}
+
+#[cfg(test)]
+mod tests {
+    use model::lattice::{Lattice, SquareLattice};
+    use model::sand_graph::SandGraph;
+    use controller::SandPileController;
+    use super::{SpanningTree, check_burning_bijection};
+
+    #[test]
+    fn superstable_round_trip() {
+        let model = SquareLattice::new().get_lattice(&[3.0, 3.0, 0.0]);
+        check_burning_bijection(&model.graph).unwrap();
+        for seed in 0..20 {
+            let tree = SpanningTree::wilson(&model.graph, seed).unwrap();
+            let superstable = tree.to_superstable(&model.graph).unwrap();
+            assert_eq!(SpanningTree::from_superstable(&model.graph, &superstable).unwrap(), tree);
+        }
+    }
+
+    #[test]
+    fn recurrent_round_trip() {
+        let model = SquareLattice::new().get_lattice(&[3.0, 3.0, 0.0]);
+        let mut controller = SandPileController::new(model);
+        for seed in 0..10 {
+            let tree = SpanningTree::wilson(&controller.model.graph, seed).unwrap();
+            let recurrent = controller.recurrent_from_tree(&tree).unwrap();
+            assert!(controller.is_recurrent(&recurrent));
+            assert_eq!(controller.tree_from_recurrent(&recurrent).unwrap(), tree);
+        }
+    }
+
+    #[test]
+    fn directed_cycle_has_no_bijection() {
+        // 1 -> 2 -> 3 -> 1, every node also leaks to sink
+        let mut graph = SandGraph::new();
+        for _ in 0..3 {
+            graph.add_node();
+        }
+        for node_idx in graph.non_sink_nodes() {
+            graph.add_edge(node_idx, node_idx % 3 + 1, 1);
+            graph.add_edge(node_idx, SandGraph::SINK_NODE, 1);
+        }
+        assert!(check_burning_bijection(&graph).is_err());
+    }
+}
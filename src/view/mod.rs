@@ -45,7 +45,8 @@ use opengl_graphics::{GLSL, OpenGL};
 use shader_version::Shaders;
 use piston::window::Window;
 use gfx::traits::FactoryExt;
-use model::sand_graph::SandGraph;
+use model::sand_graph::{SandGraph, NodeIndex};
+use model::spanning_tree::SpanningTree;
 
 impl<C: CameraController> SandPileView<C> {
     pub const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 0.1];
@@ -56,6 +57,7 @@ impl<C: CameraController> SandPileView<C> {
     pub const RED: [f32; 4] = [1.0, 0.0, 0.0, 0.5];
     pub const GREEN: [f32; 4] = [0.0, 0.5, 0.0, 0.5];
     pub const YELLOW: [f32; 4] = [1.0, 1.0, 0.0, 1.0];
+    pub const MAGENTA: [f32; 4] = [0.8, 0.0, 0.8, 0.7];
 
 
     pub fn new<F: FactoryExt<GfxResources>>(
@@ -212,6 +214,64 @@ impl<C: CameraController> SandPileView<C> {
         window.encoder.draw(slice, &self.line_pso, &data);
     }
 
+    // Arrow from node towards target, arrows to sink are skipped
+    fn build_arrow_lines<F: FactoryExt<GfxResources>>(factory: &mut F, sandpile_model: &SandPileModel, arrows: &Vec<(NodeIndex, NodeIndex)>) -> (Buffer<GfxResources, Vertex>, Slice<GfxResources>) {
+        let mut vertex_data: Vec<Vertex> = Vec::new();
+        let mut index_data: Vec<u32> = Vec::new();
+
+        for (node_idx, target_idx) in arrows {
+            if *target_idx == SandGraph::SINK_NODE {
+                continue;
+            }
+            let (coords, _) = sandpile_model.embedding.get_node_info(*node_idx);
+            let (target_coords, _) = sandpile_model.embedding.get_node_info(*target_idx);
+            let direction = vecmath::vec3_scale(vecmath::vec3_sub(target_coords, coords), 0.6);
+            let end = vecmath::vec3_add(coords, direction);
+            // head is turned by 30 degrees around z axis from the reversed direction
+            let [dx, dy, dz] = vecmath::vec3_scale(direction, -0.3);
+            let (sin, cos) = (0.5_f32, 0.75_f32.sqrt());
+            let head_1 = vecmath::vec3_add(end, [dx*cos - dy*sin, dx*sin + dy*cos, dz]);
+            let head_2 = vecmath::vec3_add(end, [dx*cos + dy*sin, -dx*sin + dy*cos, dz]);
+
+            let origin_idx = vertex_data.len() as u32;
+            for v in &[coords, end, head_1, head_2] {
+                vertex_data.push(Vertex::new(*v));
+            }
+            index_data.extend_from_slice(&[origin_idx, origin_idx + 1, origin_idx + 1, origin_idx + 2, origin_idx + 1, origin_idx + 3]);
+        }
+
+        factory.create_vertex_buffer_with_slice(&vertex_data, &index_data[..])
+    }
+
+    // arrows are built on every call, it is meant for rotors and other overlays which change between frames
+    pub fn draw_arrows<W: Window>(&mut self, window: &mut PistonWindow<W>, args: RenderArgs, sandpile_model: &SandPileModel, arrows: &Vec<(NodeIndex, NodeIndex)>) {
+        self.draw_colored_arrows(window, args, sandpile_model, arrows, Self::RED);
+    }
+
+    // every node points to its parent, edges to sink aren't drawn
+    pub fn draw_tree<W: Window>(&mut self, window: &mut PistonWindow<W>, args: RenderArgs, sandpile_model: &SandPileModel, tree: &SpanningTree) {
+        self.draw_colored_arrows(window, args, sandpile_model, &tree.edges(), Self::MAGENTA);
+    }
+
+    fn draw_colored_arrows<W: Window>(&mut self, window: &mut PistonWindow<W>, args: RenderArgs, sandpile_model: &SandPileModel, arrows: &Vec<(NodeIndex, NodeIndex)>, a_color: [f32; 4]) {
+        self.compute_view_projection(window, args);
+
+        let out_color = window.output_color.clone();
+        let out_depth = window.output_stencil.clone();
+
+        let (vbuf, slice) = Self::build_arrow_lines(&mut window.factory, sandpile_model, arrows);
+
+        let data = pipe::Data {
+            vbuf,
+            u_model_view_proj: self.view_projection,
+            a_color,
+            out_color,
+            out_depth,
+        };
+
+        window.encoder.draw(&slice, &self.line_pso, &data);
+    }
+
     pub fn draw_borders<W: Window>(&mut self, window: &mut PistonWindow<W>, args: RenderArgs, sandpile_model: &SandPileModel) {
         self.compute_view_projection(window, args);
 